@@ -0,0 +1,2744 @@
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::mem::{size_of, zeroed};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::SystemTime;
+
+use synergy_hangul_fix::caps::CapsGuard;
+use synergy_hangul_fix::config::{
+    self, exe_dir_file, Config, Location, LocationKind, CONFIG_FILE, DEFAULT_PROFILE,
+    LEGACY_CONFIG_FILE,
+};
+use synergy_hangul_fix::engine::{
+    Action, Binding, Decision, Engine, KeyEvent, KeyKind, Origin, Source, Stroke, TriggerMode,
+    IME_CMODE_NATIVE, INJECT_SIGNATURE,
+};
+use synergy_hangul_fix::keys::KeyName;
+use synergy_hangul_fix::memory::{ImeMemory, RememberScope};
+use synergy_hangul_fix::rules::{self, Method, RuleMatch, Window};
+use synergy_hangul_fix::strategy::{
+    Attempt, Outcome, Strategy, StrategyCache, StrategyStats, Verdict, DEFAULT_VERIFY_MS,
+};
+use synergy_hangul_fix::trigger::{Modifiers, Trigger};
+use synergy_hangul_fix::validate::{self, Report};
+
+use windows::core::PCWSTR;
+use windows::core::PWSTR;
+use windows::Win32::Foundation::{
+    CloseHandle, COLORREF, HINSTANCE, HWND, LPARAM, LRESULT, POINT, RECT, WPARAM,
+};
+use windows::Win32::Graphics::Gdi::{
+    BeginPaint, ClientToScreen, CreateBitmap, CreateCompatibleBitmap, CreateCompatibleDC,
+    CreateFontW, CreateSolidBrush, DeleteDC, DeleteObject, DrawTextW, EndPaint, FillRect, GetDC,
+    InvalidateRect, ReleaseDC, SelectObject, SetBkMode, SetTextColor, CLEARTYPE_QUALITY,
+    CLIP_DEFAULT_PRECIS, DEFAULT_CHARSET, DT_CENTER, DT_SINGLELINE, DT_VCENTER, FW_BOLD, HDC,
+    OUT_DEFAULT_PRECIS, PAINTSTRUCT, TRANSPARENT,
+};
+use windows::Win32::System::Console::{AttachConsole, ATTACH_PARENT_PROCESS};
+use windows::Win32::System::LibraryLoader::GetModuleHandleW;
+use windows::Win32::System::Threading::{
+    AttachThreadInput, GetCurrentThreadId, OpenProcess, QueryFullProcessImageNameW,
+    PROCESS_NAME_WIN32, PROCESS_QUERY_LIMITED_INFORMATION,
+};
+use windows::Win32::UI::Accessibility::{SetWinEventHook, UnhookWinEvent, HWINEVENTHOOK};
+use windows::Win32::UI::Input::Ime::{
+    ImmGetContext, ImmGetConversionStatus, ImmGetDefaultIMEWnd, ImmReleaseContext,
+    ImmSetConversionStatus, IME_CONVERSION_MODE, IME_SENTENCE_MODE,
+};
+use windows::Win32::UI::Input::KeyboardAndMouse::{
+    GetAsyncKeyState, GetKeyState, SendInput, INPUT, INPUT_0, INPUT_KEYBOARD, KEYBDINPUT,
+    KEYBD_EVENT_FLAGS, KEYEVENTF_EXTENDEDKEY, KEYEVENTF_KEYUP, VIRTUAL_KEY, VK_CAPITAL, VK_F13,
+    VK_RMENU,
+};
+use windows::Win32::UI::Shell::{
+    Shell_NotifyIconW, NIF_ICON, NIF_INFO, NIF_MESSAGE, NIF_TIP, NIIF_ERROR, NIIF_WARNING, NIM_ADD,
+    NIM_DELETE, NIM_MODIFY, NOTIFYICONDATAW,
+};
+use windows::Win32::UI::WindowsAndMessaging::*;
+
+// ── 상수 ──────────────────────────────────────────────────────────────────
+
+const VK_HANGUL: u16 = 0x15;
+const VK_IME_ON: u16 = 0x16;
+const VK_HANJA: u16 = 0x19;
+const VK_IME_OFF: u16 = 0x1A;
+const IMC_GETCONVERSIONMODE: usize = 0x0001;
+const IMC_SETCONVERSIONMODE: usize = 0x0002;
+const IMC_SETOPENSTATUS: usize = 0x0006;
+const WM_TRAYICON: u32 = WM_USER + 1;
+const WM_DEBUG_LOG: u32 = WM_USER + 2;
+const WM_KEY_LEARNED: u32 = WM_USER + 3;
+const WM_IME_MODE: u32 = WM_USER + 4;
+/// 훅이 결정한 IME 동작 (WPARAM: `Action::ALL` 인덱스, LPARAM: `Method::ALL` 인덱스)
+const WM_IME_ACTION: u32 = WM_USER + 5;
+/// 포그라운드가 바뀜 (WPARAM: 새 포그라운드 창)
+const WM_FOREGROUND: u32 = WM_USER + 6;
+const TRAY_ICON_ID: u32 = 1;
+/// 설정 파일 변경 감시 타이머
+const TIMER_CONFIG_WATCH: usize = 1;
+const CONFIG_WATCH_MS: u32 = 1000;
+const TIMER_VERIFY: usize = 2;
+const DEBUG_TITLE: &str = "synergy-hangul-fix [DEBUG]";
+const TIMER_INDICATOR: usize = 3;
+const TIMER_CAPS: usize = 4;
+/// 가로챈 Caps Lock 뒤 대문자 고정 상태를 다시 읽기까지 기다리는 시간 (ms)
+const CAPS_CHECK_MS: u32 = 100;
+/// 표시기 한 변의 길이 (px)
+const INDICATOR_SIZE: i32 = 36;
+
+const MODE_UNKNOWN: u32 = 0;
+const MODE_ENGLISH: u32 = 1;
+const MODE_HANGUL: u32 = 2;
+const TRAY_ICON_HANGUL: usize = 0;
+const TRAY_ICON_ENGLISH: usize = 1;
+const TRAY_ICON_DISABLED: usize = 2;
+
+const IDM_TOGGLE: u32 = 1001;
+const IDM_KEY_CAPSLOCK: u32 = 1010;
+const IDM_KEY_F13: u32 = 1011;
+const IDM_KEY_RALT: u32 = 1012;
+/// 키 감지 (IDM_KEY_LEARN_FIRST + 동작 인덱스)
+const IDM_KEY_LEARN_FIRST: u32 = 1030;
+const IDM_TAP_HOLD: u32 = 1014;
+const IDM_RELEASE_MODIFIERS: u32 = 1015;
+const IDM_DEBUG: u32 = 1020;
+const IDM_EXIT: u32 = 1099;
+/// 트리거 테이블 항목별 서브메뉴
+/// (IDM_BINDING_FIRST + 인덱스 * IDM_BINDING_STRIDE + 동작 인덱스, 마지막 칸은 삭제)
+const IDM_BINDING_FIRST: u32 = 3000;
+const IDM_BINDING_STRIDE: u32 = 16;
+/// 항목 안에서 입력 출처 선택 (+ 출처 인덱스)
+const IDM_BINDING_SOURCE: u32 = 8;
+const IDM_BINDING_REMOVE: u32 = IDM_BINDING_STRIDE - 1;
+const MAX_BINDING_ITEMS: u32 = 64;
+/// 프로필 선택 (IDM_PROFILE_FIRST는 기본 프로필, + 1 + `[[profiles]]` 인덱스)
+const IDM_PROFILE_FIRST: u32 = 4000;
+const MAX_PROFILE_ITEMS: u32 = 32;
+
+const IDC_DEBUG_EDIT: i32 = 2001;
+
+const EM_SETSEL: u32 = 0x00B1;
+const EM_REPLACESEL: u32 = 0x00C2;
+const EM_SCROLLCARET: u32 = 0x00B7;
+
+// ── 전역 상태 ─────────────────────────────────────────────────────────────
+
+/// 트리거 판단 상태 (활성화, 트리거 키, 학습 모드)
+static ENGINE: Mutex<Engine> = Mutex::new(Engine::new());
+static HOOK_HANDLE: AtomicU32 = AtomicU32::new(0);
+/// 포그라운드 변경 감시 (`EVENT_SYSTEM_FOREGROUND`)
+static WIN_EVENT_HOOK: AtomicU32 = AtomicU32::new(0);
+static MAIN_HWND: AtomicU32 = AtomicU32::new(0);
+
+/// 학습 모드에서 캡처된 키 (확인 전 임시 저장)
+static LEARNED_TRIGGER: Mutex<Option<Trigger>> = Mutex::new(None);
+/// 학습 중인 키에 연결할 동작
+static LEARN_ACTION: Mutex<Action> = Mutex::new(Action::ToggleHangul);
+/// 감지 중 팝업 윈도우 핸들
+static DETECT_HWND: AtomicU32 = AtomicU32::new(0);
+
+static DEBUG_HWND: AtomicU32 = AtomicU32::new(0);
+static DEBUG_EDIT_HWND: AtomicU32 = AtomicU32::new(0);
+static DEBUG_VISIBLE: AtomicBool = AtomicBool::new(false);
+
+static LOG_BUFFER: Mutex<Vec<String>> = Mutex::new(Vec::new());
+/// `[logging] file`로 지정한 로그 파일
+static LOG_FILE: Mutex<Option<File>> = Mutex::new(None);
+
+static CONFIG: Mutex<Option<Config>> = Mutex::new(None);
+/// 창별 한/영 상태 (`[ime] remember`)
+static IME_MEMORY: Mutex<ImeMemory> = Mutex::new(ImeMemory::new());
+/// 앱별로 통한 IME 전환 방법
+static STRATEGY_CACHE: Mutex<StrategyCache> = Mutex::new(StrategyCache::new());
+/// 방법별 성공/실패 횟수 (디버그 윈도우 제목에 표시)
+static STRATEGY_STATS: Mutex<StrategyStats> = Mutex::new(StrategyStats::new());
+/// 확인을 기다리는 시도 (포그라운드 HWND, 시도)
+static PENDING_VERIFY: Mutex<Option<(usize, Attempt)>> = Mutex::new(None);
+/// 한/영 표시기 (캐럿 옆에 잠깐 뜨는 창)
+static INDICATOR_HWND: AtomicU32 = AtomicU32::new(0);
+/// 표시기에 그릴 상태 (한글이면 true)
+static INDICATOR_NATIVE: AtomicBool = AtomicBool::new(false);
+/// `[indicator] duration_ms` (표시기를 끄면 0)
+static INDICATOR_MS: AtomicU32 = AtomicU32::new(0);
+/// `[indicator] on_focus`
+static INDICATOR_ON_FOCUS: AtomicBool = AtomicBool::new(false);
+/// 마지막으로 알아낸 한/영 상태 (`MODE_*`, 트레이 아이콘에 표시)
+static IME_MODE: AtomicU32 = AtomicU32::new(MODE_UNKNOWN);
+/// 만들어 둔 트레이 아이콘 (`TRAY_ICON_*` 순서의 HICON, 처음 쓸 때 만든다)
+static TRAY_ICONS: [AtomicU32; 3] = [AtomicU32::new(0), AtomicU32::new(0), AtomicU32::new(0)];
+/// `[caps_lock] preserve`
+static CAPS_PRESERVE: AtomicBool = AtomicBool::new(false);
+/// 알고 있는 Caps Lock 상태와 가로채기 전 상태
+static CAPS_GUARD: Mutex<CapsGuard> = Mutex::new(CapsGuard::new());
+/// `[ime] verify_ms` (0이면 확인 안 함)
+static VERIFY_MS: AtomicU32 = AtomicU32::new(DEFAULT_VERIFY_MS);
+/// 마지막으로 조회한 포그라운드 윈도우 (HWND, 정보)
+static FOREGROUND_CACHE: Mutex<Option<(usize, Window)>> = Mutex::new(None);
+/// 사용 중인 설정 파일 (시작할 때 한 번 정해진다)
+static CONFIG_LOCATION: Mutex<Option<Location>> = Mutex::new(None);
+/// 설정 파일을 읽지 못했으면 저장하지 않는다 (사용자가 고치던 파일 보호)
+static CONFIG_INVALID: AtomicBool = AtomicBool::new(false);
+/// 트레이 풍선으로 알릴 설정 문제 (요약, 오류 여부)
+static CONFIG_NOTICE: Mutex<Option<(String, bool)>> = Mutex::new(None);
+/// 변경 감시: 마지막으로 본 설정 파일 수정 시각과 내용
+static CONFIG_MTIME: Mutex<Option<SystemTime>> = Mutex::new(None);
+static CONFIG_TEXT: Mutex<Option<String>> = Mutex::new(None);
+
+fn engine() -> MutexGuard<'static, Engine> {
+    ENGINE.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn caps_guard() -> MutexGuard<'static, CapsGuard> {
+    CAPS_GUARD.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn ime_memory() -> MutexGuard<'static, ImeMemory> {
+    IME_MEMORY.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn strategy_cache() -> MutexGuard<'static, StrategyCache> {
+    STRATEGY_CACHE
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+}
+
+fn strategy_stats() -> MutexGuard<'static, StrategyStats> {
+    STRATEGY_STATS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+}
+
+// ── 설정 파일 ─────────────────────────────────────────────────────────────
+
+fn config_location() -> Location {
+    CONFIG_LOCATION
+        .lock()
+        .ok()
+        .and_then(|l| l.clone())
+        .unwrap_or_else(|| Location {
+            path: exe_dir_file(CONFIG_FILE),
+            kind: LocationKind::Portable,
+        })
+}
+
+fn config_path() -> PathBuf {
+    config_location().path
+}
+
+/// 현재 설정 (엔진 밖의 로깅·UI 옵션 포함)
+fn config() -> Config {
+    CONFIG
+        .lock()
+        .ok()
+        .and_then(|c| c.clone())
+        .unwrap_or_default()
+}
+
+fn save_config() {
+    if CONFIG_INVALID.load(Ordering::SeqCst) {
+        debug_log(&format!(
+            "[CONFIG] not saved: fix {} first (changes kept in memory)",
+            config_path().display()
+        ));
+        return;
+    }
+    let content = {
+        let mut guard = CONFIG.lock().unwrap_or_else(PoisonError::into_inner);
+        let config = guard.get_or_insert_with(Config::default);
+        config.capture(&engine());
+        config.to_toml()
+    };
+    match config::write_atomic(&config_path(), &content) {
+        Ok(()) => {
+            remember_config_file(&content);
+            debug_log(&format!("[CONFIG] saved to {}", config_path().display()));
+        }
+        Err(e) => debug_log(&format!("[CONFIG] save FAILED: {}", e)),
+    }
+}
+
+/// 마지막으로 읽거나 쓴 설정 파일 내용과 수정 시각 (변경 감시용)
+fn remember_config_file(content: &str) {
+    let modified = std::fs::metadata(config_path())
+        .and_then(|m| m.modified())
+        .ok();
+    if let Ok(mut mtime) = CONFIG_MTIME.lock() {
+        *mtime = modified;
+    }
+    if let Ok(mut text) = CONFIG_TEXT.lock() {
+        *text = Some(content.to_string());
+    }
+}
+
+/// 이전 버전의 `config.ini`가 있으면 읽어서 `config.toml`로 옮기고 `.bak`으로 바꾼다
+fn migrate_legacy_config() -> Option<Config> {
+    let legacy = exe_dir_file(LEGACY_CONFIG_FILE);
+    let content = std::fs::read_to_string(&legacy).ok()?;
+    let config = Config::from_ini(&content);
+    let toml = config.to_toml();
+    if let Err(e) = config::write_atomic(&config_path(), &toml) {
+        debug_log(&format!("[CONFIG] migration FAILED: {}", e));
+        return Some(config);
+    }
+    remember_config_file(&toml);
+    let backup = legacy.with_extension("ini.bak");
+    let _ = std::fs::rename(&legacy, &backup);
+    debug_log(&format!(
+        "[CONFIG] migrated {} → {} (old file kept as {})",
+        legacy.display(),
+        config_path().display(),
+        backup.display()
+    ));
+    Some(config)
+}
+
+/// 검증 결과를 디버그 로그에 남기고, 문제가 있으면 트레이 풍선 알림용으로 보관한다
+fn log_config_report(path: &Path, report: &Report) {
+    for diagnostic in &report.diagnostics {
+        debug_log(&format!("[CONFIG] {}:{}", path.display(), diagnostic));
+    }
+    if report.diagnostics.is_empty() {
+        return;
+    }
+    let summary = format!(
+        "{}: 오류 {}개, 경고 {}개\n{}",
+        path.display(),
+        report.errors(),
+        report.warnings(),
+        report.diagnostics[0]
+    );
+    if let Ok(mut notice) = CONFIG_NOTICE.lock() {
+        *notice = Some((summary, report.has_errors()));
+    }
+}
+
+fn load_config() {
+    let location = config_location();
+    debug_log(&format!(
+        "[CONFIG] using {} ({})",
+        location.path.display(),
+        location.kind.label()
+    ));
+    let path = location.path;
+    let config = match std::fs::read_to_string(&path) {
+        Ok(content) => {
+            remember_config_file(&content);
+            let report = validate::check(&content);
+            log_config_report(&path, &report);
+            match report.config {
+                Some(config) => config,
+                None => {
+                    // 잘못된 파일을 덮어쓰지 않도록 저장을 막아 둔다
+                    CONFIG_INVALID.store(true, Ordering::SeqCst);
+                    debug_log(&format!(
+                        "[CONFIG] {} has errors, using defaults",
+                        path.display()
+                    ));
+                    Config::default()
+                }
+            }
+        }
+        Err(_) => migrate_legacy_config().unwrap_or_default(),
+    };
+
+    if let Some(profile) = config.active_profile() {
+        debug_log(&format!("[CONFIG] loaded profile={}", profile));
+    }
+    for binding in config.bindings() {
+        debug_log(&format!(
+            "[CONFIG] loaded trigger={} ({})",
+            binding,
+            binding_display(binding)
+        ));
+    }
+    for (i, rule) in config.rules().iter().enumerate() {
+        debug_log(&format!("[CONFIG] loaded rule #{} {}", i + 1, rule));
+    }
+    if config.mode == TriggerMode::Tap {
+        debug_log(&format!(
+            "[CONFIG] loaded mode=tap hold_ms={} hold_action={}",
+            config.tap_hold.hold_ms, config.tap_hold.hold_action
+        ));
+    }
+    if config.repeat_toggles {
+        debug_log("[CONFIG] loaded repeat_toggles=true");
+    }
+    apply_config(config);
+}
+
+/// 엔진, 로그 파일, 현재 설정에 반영
+fn apply_config(config: Config) {
+    config.apply(&mut engine());
+    ime_memory().set_scope(config.ime.remember);
+    VERIFY_MS.store(config.ime.verify_ms, Ordering::SeqCst);
+    CAPS_PRESERVE.store(config.caps_lock.preserve, Ordering::SeqCst);
+    schedule_caps_check();
+    let indicator = &config.indicator;
+    let indicator_ms = if indicator.enabled {
+        indicator.duration_ms
+    } else {
+        0
+    };
+    INDICATOR_MS.store(indicator_ms, Ordering::SeqCst);
+    INDICATOR_ON_FOCUS.store(indicator.enabled && indicator.on_focus, Ordering::SeqCst);
+    open_log_file(config.logging.file.as_deref());
+    if let Ok(mut current) = CONFIG.lock() {
+        *current = Some(config);
+    }
+    update_foreground_hook();
+}
+
+/// 설정 파일이 바뀌었으면 다시 읽는다 (`WM_TIMER`에서 주기적으로 호출)
+fn poll_config_file(hwnd: HWND) {
+    let path = config_path();
+    let Ok(modified) = std::fs::metadata(&path).and_then(|m| m.modified()) else {
+        return;
+    };
+    {
+        let Ok(mut mtime) = CONFIG_MTIME.lock() else {
+            return;
+        };
+        if *mtime == Some(modified) {
+            return;
+        }
+        *mtime = Some(modified);
+    }
+    // 편집기가 저장하는 도중이면 다음 주기에 다시 읽는다
+    let Ok(content) = std::fs::read_to_string(&path) else {
+        return;
+    };
+    let unchanged = CONFIG_TEXT
+        .lock()
+        .map(|text| text.as_deref() == Some(content.as_str()))
+        .unwrap_or(false);
+    if !unchanged {
+        reload_config(hwnd, &path, content);
+    }
+}
+
+/// 바뀐 설정 파일을 검증해서 적용. 오류가 있으면 이전 설정을 유지한다.
+fn reload_config(hwnd: HWND, path: &Path, content: String) {
+    debug_log(&format!("[CONFIG] {} changed, reloading", path.display()));
+    let report = validate::check(&content);
+    log_config_report(path, &report);
+    if let Ok(mut text) = CONFIG_TEXT.lock() {
+        *text = Some(content);
+    }
+
+    let Some(new) = report.config else {
+        debug_log("[CONFIG] reload rejected, keeping previous config");
+        show_config_notice(hwnd);
+        return;
+    };
+
+    let mut old = config();
+    old.capture(&engine());
+    let changes = new.changes_from(&old);
+    let show_debug = new.logging.debug_window;
+    let debug_changed = show_debug != old.logging.debug_window;
+    apply_config(new);
+    CONFIG_INVALID.store(false, Ordering::SeqCst);
+
+    update_tray_icon(hwnd);
+    if debug_changed && show_debug != DEBUG_VISIBLE.load(Ordering::SeqCst) {
+        toggle_debug_window();
+    }
+    show_config_notice(hwnd);
+    if changes.is_empty() {
+        debug_log("[CONFIG] reloaded, no effective change");
+    } else {
+        debug_log(&format!("[CONFIG] reloaded: {}", changes.join("; ")));
+    }
+}
+
+// ── 디버그 로깅 ───────────────────────────────────────────────────────────
+
+/// 로그 한 줄을 쌓아 두고 메인 윈도우에 알린다. 키보드 훅에서도 부르므로
+/// 파일 쓰기는 여기서 하지 않고 `flush_debug_log`(메인 윈도우)에서 한다.
+fn debug_log(msg: &str) {
+    if let Ok(mut buf) = LOG_BUFFER.lock() {
+        buf.push(msg.to_string());
+    }
+    let hwnd_val = MAIN_HWND.load(Ordering::SeqCst);
+    if hwnd_val != 0 {
+        unsafe {
+            let hwnd = HWND(hwnd_val as isize as *mut _);
+            let _ = PostMessageW(hwnd, WM_DEBUG_LOG, WPARAM(0), LPARAM(0));
+        }
+    }
+}
+
+/// 로그 파일 열기 (상대 경로는 설정 파일 기준)
+fn open_log_file(path: Option<&Path>) {
+    let file = path.and_then(|path| {
+        let path = match config_path().parent() {
+            Some(dir) if path.is_relative() => dir.join(path),
+            _ => path.to_path_buf(),
+        };
+        OpenOptions::new().create(true).append(true).open(path).ok()
+    });
+    if let Ok(mut current) = LOG_FILE.lock() {
+        *current = file;
+    }
+}
+
+/// 쌓인 로그를 로그 파일과 디버그 윈도우에 쓴다 (`WM_DEBUG_LOG` 처리)
+fn flush_debug_log() {
+    let messages: Vec<String> = {
+        if let Ok(mut buf) = LOG_BUFFER.lock() {
+            buf.drain(..).collect()
+        } else {
+            return;
+        }
+    };
+
+    if messages.is_empty() {
+        return;
+    }
+
+    if let Ok(mut file) = LOG_FILE.lock() {
+        if let Some(file) = file.as_mut() {
+            for msg in &messages {
+                let _ = writeln!(file, "{}", msg);
+            }
+        }
+    }
+
+    let edit_val = DEBUG_EDIT_HWND.load(Ordering::SeqCst);
+    if edit_val == 0 {
+        return;
+    }
+
+    unsafe {
+        let edit_hwnd = HWND(edit_val as isize as *mut _);
+        for msg in &messages {
+            let line = format!("{}\r\n", msg);
+            let wide: Vec<u16> = line.encode_utf16().chain(std::iter::once(0)).collect();
+            let len = GetWindowTextLengthW(edit_hwnd);
+            SendMessageW(edit_hwnd, EM_SETSEL, WPARAM(len as usize), LPARAM(len as isize));
+            SendMessageW(
+                edit_hwnd,
+                EM_REPLACESEL,
+                WPARAM(0),
+                LPARAM(wide.as_ptr() as isize),
+            );
+        }
+        SendMessageW(edit_hwnd, EM_SCROLLCARET, WPARAM(0), LPARAM(0));
+    }
+}
+
+// ── 키보드 훅 ─────────────────────────────────────────────────────────────
+
+unsafe extern "system" fn keyboard_proc(
+    n_code: i32,
+    w_param: WPARAM,
+    l_param: LPARAM,
+) -> LRESULT {
+    if n_code >= 0 {
+        let kb = &*(l_param.0 as *const KBDLLHOOKSTRUCT);
+        let msg = w_param.0 as u32;
+
+        let kind = if msg == WM_KEYDOWN || msg == WM_SYSKEYDOWN {
+            KeyKind::Down
+        } else if msg == WM_KEYUP || msg == WM_SYSKEYUP {
+            KeyKind::Up
+        } else {
+            return CallNextHookEx(None, n_code, w_param, l_param);
+        };
+        let ev = KeyEvent {
+            kind,
+            vk: kb.vkCode,
+            scan: kb.scanCode,
+            flags: kb.flags.0,
+            origin: Origin::classify(kb.flags.0, kb.dwExtraInfo),
+            time: kb.time,
+        };
+
+        // 동작 실행(SendInput) 전에 잠금을 풀어야 한다
+        let (decision, enabled, matched, repeats, rule, stray, learning, caps_guard) = {
+            let mut engine = engine();
+            let learning = engine.is_learning();
+            let pending = engine.is_pending();
+            // 화면 재진입 시 다른 곳에서 키 업을 잃어 고착된 수식 키
+            let stray = if engine.reentered(&ev) {
+                engine.stray_modifiers(|vk| GetAsyncKeyState(vk as i32) < 0)
+            } else {
+                Vec::new()
+            };
+            let before = engine.stats().suppressed_repeats;
+            let decision = engine.process_in(&ev, foreground_window);
+            let after = engine.stats().suppressed_repeats;
+            let repeats = (after != before).then_some(after);
+            let rule = engine.rule_match().cloned();
+            // Caps Lock을 지킬 누름: 동작을 실행했거나, 탭 판정을 막 시작한 첫 누름
+            // (학습 중이거나 반복을 막은 차단은 토글을 보낸 적이 없다)
+            let caps_guard = match decision {
+                Decision::Emit(_) => true,
+                Decision::Swallow => !pending && engine.is_pending(),
+                _ => false,
+            };
+            let enabled = engine.enabled();
+            let matched = engine.lookup(&ev);
+            (
+                decision, enabled, matched, repeats, rule, stray, learning, caps_guard,
+            )
+        };
+
+        // 고착된 수식 키를 먼저 떼고, 통과시킬 이벤트는 그 뒤에 다시 주입한다
+        if !stray.is_empty() {
+            debug_log(&format!(
+                "[RECOVER] Synergy re-entry: releasing stuck {}",
+                key_list(&stray)
+            ));
+            let mut strokes = release_strokes(&stray);
+            if decision == Decision::Pass {
+                strokes.push(Stroke::replay(&ev, ev.kind));
+                send_strokes(&strokes);
+                return LRESULT(1);
+            }
+            send_strokes(&strokes);
+        }
+
+        // 차단된 자동 반복은 [KEY] 대신 누적 카운터만 남긴다
+        if let Some(total) = repeats {
+            debug_log(&format!(
+                "[REPEAT] vk=0x{:02X} ({}) autorepeat suppressed (total={})",
+                ev.vk,
+                KeyName(ev.vk),
+                total
+            ));
+            return LRESULT(1);
+        }
+
+        // 트리거와 무관한 키는 남기지 않는다 (비밀번호 등이 로그 파일에 쌓이지 않도록).
+        // 키 감지 중에는 어떤 키가 들어오는지 봐야 하므로 남긴다.
+        let logged = matched.is_some() || learning;
+        if logged && ev.is_down() && !matches!(decision, Decision::Learned(_)) {
+            let matched = match matched {
+                Some(binding) => binding.to_string(),
+                None => "-".to_string(),
+            };
+            debug_log(&format!(
+                "[KEY] vk=0x{:02X} ({}) scan=0x{:04X} flags=0x{:08X} origin={:?} | enabled={} match={}",
+                ev.vk,
+                KeyName(ev.vk),
+                ev.scan,
+                ev.flags,
+                ev.origin,
+                enabled,
+                matched
+            ));
+        }
+
+        if let (true, Some(_), Some(rule)) = (ev.is_down(), matched, &rule) {
+            debug_log(&format!("[RULE] matched {}", rule));
+        }
+
+        if ev.vk == VK_CAPITAL.0 as u32 && ev.origin != Origin::Own {
+            if caps_guard {
+                guard_caps_lock();
+            } else if matches!(decision, Decision::Replay(_))
+                || (ev.is_down() && decision == Decision::Pass)
+            {
+                // 트리거가 아니거나 홀드로 확정된 Caps Lock은 원래 기능이다
+                caps_lock_passed();
+            }
+        }
+
+        match decision {
+            Decision::Pass => {}
+            Decision::Swallow => return LRESULT(1),
+            Decision::Emit(action) => {
+                post_ime_action(action, rule.map_or(Method::Auto, |r| r.rule.method));
+                return LRESULT(1);
+            }
+            Decision::Replay(strokes) => {
+                debug_log(&format!("[ACTION] trigger held → replay {:?}", strokes));
+                send_strokes(&strokes);
+                return LRESULT(1);
+            }
+            Decision::Learned(trigger) => {
+                if let Ok(mut learned) = LEARNED_TRIGGER.lock() {
+                    *learned = Some(trigger);
+                }
+                debug_log(&format!(
+                    "[LEARN] captured {} vk=0x{:02X} scan=0x{:04X} ext={}",
+                    trigger,
+                    ev.vk,
+                    ev.scan,
+                    ev.is_extended()
+                ));
+                let hwnd_val = MAIN_HWND.load(Ordering::SeqCst);
+                if hwnd_val != 0 {
+                    let hwnd = HWND(hwnd_val as isize as *mut _);
+                    let _ = PostMessageW(hwnd, WM_KEY_LEARNED, WPARAM(0), LPARAM(0));
+                }
+                return LRESULT(1);
+            }
+        }
+    }
+
+    CallNextHookEx(None, n_code, w_param, l_param)
+}
+
+/// 훅 안에서는 판단만 하고, 다른 프로세스로 메시지를 보내는 IME 조작은 메인 윈도우에서
+/// 한다 (훅이 `LowLevelHooksTimeout`을 넘기면 Windows가 훅을 말없이 제거한다)
+fn post_ime_action(action: Action, method: Method) {
+    let hwnd_val = MAIN_HWND.load(Ordering::SeqCst);
+    if hwnd_val == 0 {
+        return;
+    }
+    let action = Action::ALL.iter().position(|&a| a == action).unwrap_or(0);
+    let method = Method::ALL.iter().position(|&m| m == method).unwrap_or(0);
+    unsafe {
+        let hwnd = HWND(hwnd_val as isize as *mut _);
+        let _ = PostMessageW(hwnd, WM_IME_ACTION, WPARAM(action), LPARAM(method as isize));
+    }
+}
+
+/// `WM_IME_ACTION` 처리 (메인 윈도우 스레드)
+fn run_action(hwnd: HWND, action: Action, method: Method) {
+    debug_log(&format!(
+        "[ACTION] {} → send_ime_action(method={})",
+        action, method
+    ));
+    let fg_hwnd = unsafe { GetForegroundWindow() };
+    debug_log(&format!("[IMM] GetForegroundWindow → HWND={:?}", fg_hwnd.0));
+    let process = foreground_window().map(|w| w.process).unwrap_or_default();
+    let attempt = send_ime_action(fg_hwnd, process, action, method);
+    if action != Action::ConvertHanja {
+        remember_ime_state(attempt.as_ref().and_then(|a| a.conversion));
+    }
+    if let Some(attempt) = attempt {
+        if let Some(conversion) = attempt
+            .conversion
+            .filter(|_| action != Action::ConvertHanja)
+        {
+            set_ime_mode(conversion & IME_CMODE_NATIVE != 0);
+            show_indicator(conversion & IME_CMODE_NATIVE != 0);
+        }
+        // 한자 변환은 곧 풀리는 상태라 다시 읽어 볼 수 없다
+        let verify = action != Action::ConvertHanja;
+        schedule_verify(hwnd, fg_hwnd, attempt, verify);
+    }
+}
+
+// ── Caps Lock 상태 보존 ───────────────────────────────────────────────────
+
+/// 전역 Caps Lock 토글 상태. 키보드 상태는 입력 큐(스레드)마다 따로라서
+/// 포그라운드 윈도우의 스레드에 잠시 붙어서 읽는다. 메인 스레드에서만 부른다.
+fn caps_lock_on() -> bool {
+    unsafe {
+        let current = GetCurrentThreadId();
+        let target = GetWindowThreadProcessId(GetForegroundWindow(), None);
+        let attached =
+            target != 0 && target != current && AttachThreadInput(current, target, true).as_bool();
+        let on = GetKeyState(VK_CAPITAL.0 as i32) & 1 != 0;
+        if attached {
+            let _ = AttachThreadInput(current, target, false);
+        }
+        on
+    }
+}
+
+/// `CAPS_CHECK_MS` 뒤에 메인 윈도우에서 Caps Lock 상태를 다시 읽도록 타이머를 (다시) 건다
+fn schedule_caps_check() {
+    if !CAPS_PRESERVE.load(Ordering::SeqCst) {
+        return;
+    }
+    let main = MAIN_HWND.load(Ordering::SeqCst);
+    if main != 0 {
+        unsafe {
+            SetTimer(
+                HWND(main as isize as *mut _),
+                TIMER_CAPS,
+                CAPS_CHECK_MS,
+                None,
+            );
+        }
+    }
+}
+
+/// 키보드 훅에서 동작을 실행하거나 탭 판정을 시작한 Caps Lock 누름마다 호출: 처음이면
+/// 알고 있던 누름 전 상태를 기억하고, 조금 뒤 다시 읽어 비교한다
+fn guard_caps_lock() {
+    if CAPS_PRESERVE.load(Ordering::SeqCst) {
+        caps_guard().guard();
+        schedule_caps_check();
+    }
+}
+
+/// 키보드 훅에서 Caps Lock 다운이 Windows로 갈 때 호출: 되돌리지 않고, 바뀐 상태를 다시 읽는다
+fn caps_lock_passed() {
+    if CAPS_PRESERVE.load(Ordering::SeqCst) {
+        caps_guard().passed();
+        schedule_caps_check();
+    }
+}
+
+/// 전역 Caps Lock 상태를 읽고, Synergy가 트리거 누름 뒤에서 바꿔 놓았으면 Caps Lock을
+/// 한 번 더 눌러 되돌린다
+fn restore_caps_lock(hwnd: HWND) {
+    unsafe {
+        let _ = KillTimer(hwnd, TIMER_CAPS);
+    }
+    if !CAPS_PRESERVE.load(Ordering::SeqCst) {
+        return;
+    }
+    let now = caps_lock_on();
+    if !caps_guard().check(now) {
+        return;
+    }
+    debug_log(&format!(
+        "[CAPS] Caps Lock changed {} → {} behind the trigger → restoring",
+        if now { "off" } else { "on" },
+        if now { "on" } else { "off" }
+    ));
+    let vk = VK_CAPITAL.0 as u32;
+    send_strokes(&[Stroke::key(vk, KeyKind::Down), Stroke::key(vk, KeyKind::Up)]);
+}
+
+// ── 수식 키 고착 복구 ─────────────────────────────────────────────────────
+
+/// 키 업 전에 끼워 넣는 미할당 가상 키. Alt/Win을 단독으로 뗀 것으로 보이면
+/// 메뉴 막대나 시작 메뉴가 열리므로 그사이에 다른 키가 눌린 것처럼 만든다.
+const VK_MENU_MASK: u32 = 0xE8;
+
+/// `vks`의 키 업 (앞에 메뉴 억제용 키 한 번)
+fn release_strokes(vks: &[u32]) -> Vec<Stroke> {
+    let mut strokes = vec![
+        Stroke::key(VK_MENU_MASK, KeyKind::Down),
+        Stroke::key(VK_MENU_MASK, KeyKind::Up),
+    ];
+    strokes.extend(vks.iter().map(|&vk| Stroke::key(vk, KeyKind::Up)));
+    strokes
+}
+
+fn key_list(vks: &[u32]) -> String {
+    vks.iter()
+        .map(|&vk| KeyName(vk).to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// 트레이의 "수식 키 모두 떼기": 엔진의 눌림 상태를 비우고, Windows가 눌려 있다고 보는
+/// 수식 키를 모두 뗀다
+fn release_all_modifiers() {
+    let down: Vec<u32> = Modifiers::VKS
+        .iter()
+        .map(|&(vk, _)| vk)
+        .filter(|&vk| unsafe { GetAsyncKeyState(vk as i32) } < 0)
+        .collect();
+    let mut strokes = engine().release_all(&down);
+    debug_log(&format!(
+        "[RECOVER] release all modifiers: {}",
+        if down.is_empty() {
+            "none held".to_string()
+        } else {
+            key_list(&down)
+        }
+    ));
+    strokes.extend(release_strokes(&down));
+    send_strokes(&strokes);
+}
+
+/// 포그라운드 윈도우의 클래스와 프로세스 이름 (앱별 규칙용, 같은 윈도우면 캐시)
+fn foreground_window() -> Option<Window> {
+    let hwnd = unsafe { GetForegroundWindow() };
+    if hwnd.0.is_null() {
+        return None;
+    }
+    if let Ok(cache) = FOREGROUND_CACHE.lock() {
+        if let Some((cached, window)) = cache.as_ref() {
+            if *cached == hwnd.0 as usize {
+                return Some(window.clone());
+            }
+        }
+    }
+    let window = window_info(hwnd);
+    if let Ok(mut cache) = FOREGROUND_CACHE.lock() {
+        *cache = Some((hwnd.0 as usize, window.clone()));
+    }
+    Some(window)
+}
+
+/// 윈도우 클래스와 실행 파일 이름 (알 수 없으면 빈 문자열)
+fn window_info(hwnd: HWND) -> Window {
+    unsafe {
+        let mut class = [0u16; 256];
+        let len = GetClassNameW(hwnd, &mut class);
+        let class = String::from_utf16_lossy(&class[..len.max(0) as usize]);
+
+        let mut pid = 0u32;
+        GetWindowThreadProcessId(hwnd, Some(&mut pid));
+        let mut process = String::new();
+        if let Ok(handle) = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid) {
+            let mut path = [0u16; 520];
+            let mut size = path.len() as u32;
+            if QueryFullProcessImageNameW(
+                handle,
+                PROCESS_NAME_WIN32,
+                PWSTR(path.as_mut_ptr()),
+                &mut size,
+            )
+            .is_ok()
+            {
+                let path = String::from_utf16_lossy(&path[..size as usize]);
+                process = path.rsplit('\\').next().unwrap_or_default().to_string();
+            }
+            let _ = CloseHandle(handle);
+        }
+
+        Window { process, class }
+    }
+}
+
+// ── 포커스 변경 ───────────────────────────────────────────────────────────
+
+fn ime_state_label(native: bool) -> &'static str {
+    if native {
+        "한"
+    } else {
+        "A"
+    }
+}
+
+/// 트리거로 바꾼 뒤의 변환 모드를 포그라운드 창 몫으로 기록한다
+fn remember_ime_state(conversion: Option<u32>) {
+    if ime_memory().scope() == RememberScope::Off {
+        return;
+    }
+    let hwnd = unsafe { GetForegroundWindow() };
+    let Some(window) = foreground_window() else {
+        return;
+    };
+    let Some(conversion) = conversion else {
+        debug_log(&format!(
+            "[MEMORY] state unknown for {}, not recorded",
+            window
+        ));
+        return;
+    };
+    let native = conversion & IME_CMODE_NATIVE != 0;
+    ime_memory().record(hwnd.0 as usize, &window.process, native);
+    debug_log(&format!(
+        "[MEMORY] recorded {} for {}",
+        ime_state_label(native),
+        window
+    ));
+}
+
+/// 창별 기억, `mode`가 있는 규칙, 포커스 때 표시기 중 하나라도 쓸 때만 포그라운드 변경을
+/// 감시한다 (설정을 읽을 때마다 호출)
+fn update_foreground_hook() {
+    let wanted = ime_memory().scope() != RememberScope::Off
+        || engine().rules().iter().any(|rule| rule.mode.is_some())
+        || INDICATOR_ON_FOCUS.load(Ordering::SeqCst);
+    let raw = WIN_EVENT_HOOK.load(Ordering::SeqCst);
+    if wanted == (raw != 0) {
+        return;
+    }
+    unsafe {
+        if wanted {
+            let hook = SetWinEventHook(
+                EVENT_SYSTEM_FOREGROUND,
+                EVENT_SYSTEM_FOREGROUND,
+                None,
+                Some(win_event_proc),
+                0,
+                0,
+                WINEVENT_OUTOFCONTEXT | WINEVENT_SKIPOWNPROCESS,
+            );
+            WIN_EVENT_HOOK.store(hook.0 as usize as u32, Ordering::SeqCst);
+            debug_log(&format!(
+                "[FOCUS] foreground hook installed={}",
+                !hook.0.is_null()
+            ));
+        } else {
+            let _ = UnhookWinEvent(HWINEVENTHOOK(raw as isize as *mut _));
+            WIN_EVENT_HOOK.store(0, Ordering::SeqCst);
+            debug_log("[FOCUS] foreground hook removed");
+        }
+    }
+}
+
+/// 포그라운드 변경 알림은 메인 윈도우로 넘기고 바로 돌아온다 (IME 조회는 `on_foreground`)
+unsafe extern "system" fn win_event_proc(
+    _hook: HWINEVENTHOOK,
+    _event: u32,
+    hwnd: HWND,
+    id_object: i32,
+    _id_child: i32,
+    _thread: u32,
+    _time: u32,
+) {
+    // OBJID_WINDOW(0)인 창 자체의 이벤트만
+    if hwnd.0.is_null() || id_object != 0 {
+        return;
+    }
+    let main = MAIN_HWND.load(Ordering::SeqCst);
+    if main != 0 {
+        let main = HWND(main as isize as *mut _);
+        let _ = PostMessageW(main, WM_FOREGROUND, WPARAM(hwnd.0 as usize), LPARAM(0));
+    }
+}
+
+/// 포그라운드가 바뀌면 앱별 규칙의 `mode`로, 없으면 그 창에 기록해 둔 한/영 상태로 맞춘다
+/// (`WM_FOREGROUND` 처리, `main`은 메인 윈도우)
+unsafe fn on_foreground(main: HWND, hwnd: HWND) {
+    // 처리하기 전에 다시 바뀌었으면 뒤따르는 알림에 맡긴다
+    if GetForegroundWindow() != hwnd {
+        return;
+    }
+    let on_focus = INDICATOR_ON_FOCUS.load(Ordering::SeqCst);
+    let window = window_info(hwnd);
+
+    let focus_mode = {
+        let engine = engine();
+        rules::find_mode(engine.rules(), &window).map(|(index, rule, mode)| {
+            let rule_match = RuleMatch {
+                index,
+                rule: rule.clone(),
+                window: window.clone(),
+            };
+            (rule_match, mode)
+        })
+    };
+    if let Some((rule_match, mode)) = focus_mode {
+        let current = query_conversion_mode(hwnd);
+        if current.map(|c| c & IME_CMODE_NATIVE != 0) == Some(mode.is_native()) {
+            debug_log(&format!("[FOCUS] {} → already {}", rule_match, mode));
+        } else {
+            let method = rule_match.rule.method;
+            let ok = switch_native_mode(main, hwnd, &window, mode.is_native(), method);
+            debug_log(&format!(
+                "[FOCUS] {} → set {} (ok={})",
+                rule_match, mode, ok
+            ));
+        }
+        set_ime_mode(mode.is_native());
+        if on_focus {
+            show_indicator(mode.is_native());
+        }
+        return;
+    }
+
+    let hwnd_val = hwnd.0 as usize;
+    let current = query_conversion_mode(hwnd);
+    let restore = ime_memory().restore(
+        hwnd_val,
+        &window.process,
+        current.map(|c| c & IME_CMODE_NATIVE != 0),
+    );
+    if let Some(native) = restore {
+        let ok = switch_native_mode(main, hwnd, &window, native, Method::Auto);
+        debug_log(&format!(
+            "[MEMORY] restored {} for {} (ok={})",
+            ime_state_label(native),
+            window,
+            ok
+        ));
+        set_ime_mode(native);
+        if on_focus {
+            show_indicator(native);
+        }
+    } else if let Some(conversion) = current {
+        set_ime_mode(conversion & IME_CMODE_NATIVE != 0);
+        if on_focus {
+            show_indicator(conversion & IME_CMODE_NATIVE != 0);
+        }
+    }
+}
+
+/// 창의 한/영 모드를 트리거와 같은 방법 순서(`send_ime_action`)로 맞추고, 결과를
+/// 앱별 캐시에 남긴 뒤 확인을 예약한다
+fn switch_native_mode(
+    main: HWND,
+    hwnd: HWND,
+    window: &Window,
+    native: bool,
+    method: Method,
+) -> bool {
+    let action = if native {
+        Action::SetHangul
+    } else {
+        Action::SetEnglish
+    };
+    let Some(attempt) = send_ime_action(hwnd, window.process.clone(), action, method) else {
+        return false;
+    };
+    schedule_verify(main, hwnd, attempt, true);
+    true
+}
+
+/// 엔진이 요청한 키 입력 주입 (탭/홀드 모드의 홀드 재현)
+fn send_strokes(strokes: &[Stroke]) {
+    let inputs: Vec<INPUT> = strokes
+        .iter()
+        .map(|stroke| {
+            let mut flags = KEYBD_EVENT_FLAGS::default();
+            if stroke.extended {
+                flags |= KEYEVENTF_EXTENDEDKEY;
+            }
+            if stroke.kind == KeyKind::Up {
+                flags |= KEYEVENTF_KEYUP;
+            }
+            INPUT {
+                r#type: INPUT_KEYBOARD,
+                Anonymous: INPUT_0 {
+                    ki: KEYBDINPUT {
+                        wVk: VIRTUAL_KEY(stroke.vk as u16),
+                        wScan: stroke.scan as u16,
+                        dwFlags: flags,
+                        time: 0,
+                        // 훅에서 자신이 주입한 입력을 알아보는 표식
+                        dwExtraInfo: INJECT_SIGNATURE,
+                    },
+                },
+            }
+        })
+        .collect();
+
+    unsafe {
+        SendInput(&inputs, size_of::<INPUT>() as i32);
+    }
+}
+
+/// IME 동작 실행: 앱에 통하는 방법을 찾을 때까지 IMM 컨텍스트, 기본 IME 창,
+/// 키 주입 순서로 시도한다 (`strategy` 모듈). 앱별 규칙의 `method`로 쓸 방법을 줄일 수 있다.
+/// 적용된 방법과 바꾼 뒤의 변환 모드(알 수 있으면)를 돌려준다.
+fn send_ime_action(
+    fg_hwnd: HWND,
+    process: String,
+    action: Action,
+    method: Method,
+) -> Option<Attempt> {
+    // 한자 변환은 한자 키 주입만 통한다. 앱별로 기억한 방법과 통계에는 섞지 않는다.
+    if action == Action::ConvertHanja {
+        if !Strategy::allowed(method).contains(&Strategy::SendInput) {
+            debug_log(&format!(
+                "[STRATEGY] {} needs send_input, not allowed by method={}",
+                action, method
+            ));
+            return None;
+        }
+        let outcome = unsafe { send_input_strategy(fg_hwnd, action) };
+        debug_log(&format!("[STRATEGY] {} → {}", Strategy::SendInput, outcome));
+        return None;
+    }
+    let plan = strategy_cache().plan(&process, method);
+    try_strategies(fg_hwnd, process, action, plan)
+}
+
+/// `plan`의 방법을 차례로 시도해서 처음 적용된 것을 돌려준다
+fn try_strategies(
+    fg_hwnd: HWND,
+    process: String,
+    action: Action,
+    plan: Vec<Strategy>,
+) -> Option<Attempt> {
+    debug_log(&format!(
+        "[STRATEGY] {} {} → try {}",
+        if process.is_empty() { "?" } else { &process },
+        action,
+        plan.iter().map(|s| s.name()).collect::<Vec<_>>().join(", ")
+    ));
+
+    for (i, &strategy) in plan.iter().enumerate() {
+        let outcome = unsafe {
+            match strategy {
+                Strategy::Imm => imm_strategy(fg_hwnd, action),
+                Strategy::ImeWindow => ime_window_strategy(fg_hwnd, action),
+                Strategy::SendInput => send_input_strategy(fg_hwnd, action),
+            }
+        };
+        debug_log(&format!("[STRATEGY] {} → {}", strategy, outcome));
+        match outcome {
+            Outcome::Applied(conversion) => {
+                strategy_cache().record(&process, strategy);
+                return Some(Attempt {
+                    process,
+                    strategy,
+                    conversion,
+                    remaining: plan[i + 1..].to_vec(),
+                });
+            }
+            Outcome::Failed(_) => strategy_stats().record(strategy, Verdict::Failed),
+        }
+    }
+
+    strategy_cache().forget(&process);
+    debug_log("[STRATEGY] every method failed → nothing changed");
+    update_debug_title();
+    None
+}
+
+// ── 전환 확인 ─────────────────────────────────────────────────────────────
+
+/// `verify_ms` 뒤에 메인 윈도우(`hwnd`)의 타이머로 변환 모드를 다시 읽도록 예약한다.
+/// 기다리지 않고 바로 돌아오므로 `verify_ms`가 길어도 입력이 막히지 않는다.
+/// 확인할 수 없는 시도는 바로 "확인 못 함"으로 센다.
+fn schedule_verify(hwnd: HWND, fg_hwnd: HWND, attempt: Attempt, verify: bool) {
+    let verify_ms = VERIFY_MS.load(Ordering::SeqCst);
+    if !verify || verify_ms == 0 || attempt.conversion.is_none() {
+        strategy_stats().record(attempt.strategy, Verdict::Unknown);
+        update_debug_title();
+        return;
+    }
+    let replaced = PENDING_VERIFY
+        .lock()
+        .ok()
+        .and_then(|mut pending| pending.replace((fg_hwnd.0 as usize, attempt)));
+    if let Some((_, replaced)) = replaced {
+        // 확인 전에 다시 전환함
+        strategy_stats().record(replaced.strategy, Verdict::Unknown);
+    }
+    unsafe {
+        SetTimer(hwnd, TIMER_VERIFY, verify_ms, None);
+    }
+}
+
+/// 변환 모드를 다시 읽어 적용됐는지 확인하고, 그대로면 다음 방법으로 넘어간다
+fn verify_ime_action(hwnd: HWND) {
+    unsafe {
+        let _ = KillTimer(hwnd, TIMER_VERIFY);
+    }
+    let Some((fg_val, attempt)) = PENDING_VERIFY.lock().ok().and_then(|mut p| p.take()) else {
+        return;
+    };
+    let fg_hwnd = HWND(fg_val as isize as *mut _);
+    let verdict = if unsafe { GetForegroundWindow() } != fg_hwnd {
+        debug_log("[VERIFY] foreground changed before the check");
+        Verdict::Unknown
+    } else {
+        let conversion = unsafe { query_conversion_mode(fg_hwnd) };
+        if let Some(conversion) = conversion {
+            set_ime_mode(conversion & IME_CMODE_NATIVE != 0);
+        }
+        attempt.check(conversion)
+    };
+    strategy_stats().record(attempt.strategy, verdict);
+    debug_log(&format!(
+        "[VERIFY] {} on {} → {} ({})",
+        attempt.strategy,
+        if attempt.process.is_empty() {
+            "?"
+        } else {
+            &attempt.process
+        },
+        verdict,
+        strategy_stats()
+    ));
+    update_debug_title();
+
+    if verdict != Verdict::Failed {
+        return;
+    }
+    // 호출은 성공했지만 앱이 무시함: 다음에는 처음부터 다시 찾는다
+    strategy_cache().forget(&attempt.process);
+    let Some(action) = attempt.retry_action() else {
+        debug_log("[VERIFY] no method left");
+        return;
+    };
+    let remaining = attempt.remaining.clone();
+    if let Some(next) = try_strategies(fg_hwnd, attempt.process, action, remaining) {
+        schedule_verify(hwnd, fg_hwnd, next, true);
+    }
+}
+
+/// 디버그 윈도우 제목에 방법별 성공/실패 횟수를 표시한다
+fn update_debug_title() {
+    let hwnd_val = DEBUG_HWND.load(Ordering::SeqCst);
+    if hwnd_val == 0 {
+        return;
+    }
+    let title = wide_string(&format!("{} — {}", DEBUG_TITLE, strategy_stats()));
+    unsafe {
+        let _ = SetWindowTextW(HWND(hwnd_val as isize as *mut _), wptr(&title));
+    }
+}
+
+/// 1단계: 포커스가 있는 자식 창(없으면 포그라운드 창)의 IMM 컨텍스트로 변환 모드를 바꾼다
+unsafe fn imm_strategy(fg_hwnd: HWND, action: Action) -> Outcome {
+    if fg_hwnd.0.is_null() {
+        return Outcome::Failed("no foreground window");
+    }
+    let thread = GetWindowThreadProcessId(fg_hwnd, None);
+    let mut info = GUITHREADINFO {
+        cbSize: size_of::<GUITHREADINFO>() as u32,
+        ..Default::default()
+    };
+    let mut targets = Vec::with_capacity(2);
+    if GetGUIThreadInfo(thread, &mut info).is_ok() && !info.hwndFocus.0.is_null() {
+        targets.push(info.hwndFocus);
+    }
+    if !targets.contains(&fg_hwnd) {
+        targets.push(fg_hwnd);
+    }
+
+    for hwnd in targets {
+        let himc = ImmGetContext(hwnd);
+        debug_log(&format!(
+            "[IMM] ImmGetContext(HWND={:?}) → HIMC={:?}",
+            hwnd.0, himc.0
+        ));
+        if himc.0 as usize == 0 {
+            continue;
+        }
+
+        let mut conversion = IME_CONVERSION_MODE::default();
+        let mut sentence = IME_SENTENCE_MODE::default();
+        let ok = ImmGetConversionStatus(himc, Some(&mut conversion), Some(&mut sentence));
+        debug_log(&format!(
+            "[IMM] ImmGetConversionStatus → ok={} conversion=0x{:08X} sentence=0x{:08X}",
+            ok.as_bool(),
+            conversion.0,
+            sentence.0
+        ));
+
+        let outcome = match action.apply_conversion(conversion.0) {
+            _ if !ok.as_bool() => Outcome::Failed("ImmGetConversionStatus failed"),
+            Some(new) if new == conversion.0 => {
+                debug_log("[IMM] already in requested mode → no change");
+                Outcome::Applied(Some(new))
+            }
+            Some(new) => {
+                let new_conversion = IME_CONVERSION_MODE(new);
+                let set_ok = ImmSetConversionStatus(himc, new_conversion, sentence);
+                debug_log(&format!(
+                    "[IMM] ImmSetConversionStatus → ok={} new_conversion=0x{:08X}",
+                    set_ok.as_bool(),
+                    new_conversion.0
+                ));
+                if set_ok.as_bool() {
+                    Outcome::Applied(Some(new))
+                } else {
+                    Outcome::Failed("ImmSetConversionStatus failed")
+                }
+            }
+            None => Outcome::Failed("not a conversion mode change"),
+        };
+        let _ = ImmReleaseContext(hwnd, himc);
+        return outcome;
+    }
+    Outcome::Failed("no IMM context")
+}
+
+/// 2단계: 기본 IME 창에 `WM_IME_CONTROL`로 변환 모드를 바꾼다 (다른 프로세스의 창에도 동작)
+unsafe fn ime_window_strategy(fg_hwnd: HWND, action: Action) -> Outcome {
+    let Some(current) = query_conversion_mode(fg_hwnd) else {
+        return Outcome::Failed("no default IME window");
+    };
+    let Some(new) = action.apply_conversion(current) else {
+        return Outcome::Failed("not a conversion mode change");
+    };
+    if new == current {
+        debug_log("[IMM] already in requested mode → no change");
+        return Outcome::Applied(Some(new));
+    }
+    // 한글로 바꾸려면 IME가 열려 있어야 한다
+    if new & IME_CMODE_NATIVE != 0 && ime_control(fg_hwnd, IMC_SETOPENSTATUS, 1).is_none() {
+        return Outcome::Failed("IMC_SETOPENSTATUS failed");
+    }
+    if !set_conversion_mode(fg_hwnd, new) {
+        return Outcome::Failed("IMC_SETCONVERSIONMODE failed");
+    }
+    Outcome::Applied(Some(new))
+}
+
+/// 3단계: IME 키를 주입한다. 현재 모드를 알면 필요할 때만 `VK_HANGUL`,
+/// 모르면 한글로·영문으로는 `VK_IME_ON`/`VK_IME_OFF`
+unsafe fn send_input_strategy(fg_hwnd: HWND, action: Action) -> Outcome {
+    let current = query_conversion_mode(fg_hwnd);
+    let fallback_vk = match (action, current) {
+        (Action::ToggleHangul, _) => Some(VK_HANGUL),
+        (Action::ConvertHanja, _) => Some(VK_HANJA),
+        (_, Some(conversion)) => {
+            let target = action.apply_conversion(conversion);
+            (target != Some(conversion)).then_some(VK_HANGUL)
+        }
+        (Action::SetHangul, None) => Some(VK_IME_ON),
+        (Action::SetEnglish, None) => Some(VK_IME_OFF),
+    };
+
+    match fallback_vk {
+        Some(vk) => {
+            debug_log(&format!(
+                "[FALLBACK] SendInput vk=0x{:02X} ({}) (current={:?})",
+                vk,
+                KeyName(vk as u32),
+                current
+            ));
+            send_strokes(&[
+                Stroke::key(vk as u32, KeyKind::Down),
+                Stroke::key(vk as u32, KeyKind::Up),
+            ]);
+        }
+        None => debug_log("[FALLBACK] already in requested mode → no key sent"),
+    }
+    // 주입한 키는 아직 처리 전이므로 읽은 모드에서 결과를 계산한다
+    Outcome::Applied(current.and_then(|c| action.apply_conversion(c)))
+}
+
+/// 윈도우의 기본 IME 창에 `WM_IME_CONTROL`을 보낸다 (응답이 없으면 `None`)
+unsafe fn ime_control(hwnd: HWND, command: usize, value: isize) -> Option<usize> {
+    if hwnd.0.is_null() {
+        return None;
+    }
+    let ime_hwnd = ImmGetDefaultIMEWnd(hwnd);
+    if ime_hwnd.0.is_null() {
+        return None;
+    }
+    let mut result = 0usize;
+    let sent = SendMessageTimeoutW(
+        ime_hwnd,
+        WM_IME_CONTROL,
+        WPARAM(command),
+        LPARAM(value),
+        SMTO_ABORTIFHUNG,
+        100,
+        Some(&mut result),
+    );
+    (sent.0 != 0).then_some(result)
+}
+
+/// 윈도우의 기본 IME 창으로 변환 모드를 바꾼다 (다른 프로세스의 창에도 동작)
+unsafe fn set_conversion_mode(hwnd: HWND, conversion: u32) -> bool {
+    let ok = ime_control(hwnd, IMC_SETCONVERSIONMODE, conversion as isize).is_some();
+    debug_log(&format!(
+        "[IMM] WM_IME_CONTROL(IMC_SETCONVERSIONMODE, 0x{:08X}) → ok={}",
+        conversion, ok
+    ));
+    ok
+}
+
+/// 포그라운드 윈도우의 기본 IME 윈도우에 변환 모드를 묻는다 (`WM_IME_CONTROL`)
+unsafe fn query_conversion_mode(fg_hwnd: HWND) -> Option<u32> {
+    let result = ime_control(fg_hwnd, IMC_GETCONVERSIONMODE, 0);
+    debug_log(&format!(
+        "[IMM] WM_IME_CONTROL(IMC_GETCONVERSIONMODE) → ok={} conversion=0x{:08X}",
+        result.is_some(),
+        result.unwrap_or(0)
+    ));
+    result.map(|conversion| conversion as u32)
+}
+
+// ── 트레이 아이콘 관리 ────────────────────────────────────────────────────
+
+/// `Ctrl+RightAlt`처럼 수식 키를 붙인 표시 이름 (스캔 코드가 있으면 `LeftAlt [0xE038]`)
+fn trigger_display(trigger: Trigger) -> String {
+    let mut key_name = KeyName(trigger.key).to_string();
+    if let Some(scan) = trigger.scan {
+        key_name = format!("{} [{}]", key_name, scan);
+    }
+    if trigger.mods.is_empty() {
+        key_name
+    } else {
+        format!("{}+{}", trigger.mods, key_name)
+    }
+}
+
+/// `RightCtrl → 한자 변환` (출처 제한이 있으면 `[Synergy]`/`[로컬]` 접두사)
+fn binding_display(binding: Binding) -> String {
+    let source = match binding.source {
+        Source::Any => "",
+        Source::Injected => "[Synergy] ",
+        Source::Local => "[로컬] ",
+    };
+    format!(
+        "{}{} → {}",
+        source,
+        trigger_display(binding.trigger),
+        binding.action.label()
+    )
+}
+
+fn make_tooltip() -> [u16; 128] {
+    let (enabled, bindings, mode) = {
+        let engine = engine();
+        (engine.enabled(), engine.bindings().to_vec(), engine.mode())
+    };
+    let config = config();
+    let status = match (enabled, config.profiles.is_empty()) {
+        (true, true) => "ON".to_string(),
+        (false, true) => "OFF".to_string(),
+        (enabled, false) => format!(
+            "{} · {}",
+            if enabled { "ON" } else { "OFF" },
+            config.active_profile().unwrap_or(DEFAULT_PROFILE)
+        ),
+    };
+    let key_name = match bindings.as_slice() {
+        [] => "트리거 없음".to_string(),
+        [only] if only.action == Action::ToggleHangul => trigger_display(only.trigger),
+        _ => bindings
+            .iter()
+            .map(|b| binding_display(*b))
+            .collect::<Vec<_>>()
+            .join(", "),
+    };
+
+    let text = match mode {
+        TriggerMode::Press => format!("synergy-hangul-fix [{}] - {}", status, key_name),
+        TriggerMode::Tap => format!("synergy-hangul-fix [{}] - {} (탭)", status, key_name),
+    };
+    // 사용 중인 설정 파일은 둘째 줄에 (길면 앞부분을 줄여서) 표시
+    let mut wide: Vec<u16> = text.encode_utf16().take(80).collect();
+    wide.extend("\n설정: ".encode_utf16());
+    let path: Vec<u16> = config_path().display().to_string().encode_utf16().collect();
+    let room = 127 - wide.len();
+    if path.len() > room {
+        wide.push('…' as u16);
+        wide.extend_from_slice(&path[path.len() - (room - 1)..]);
+    } else {
+        wide.extend_from_slice(&path);
+    }
+    let mut tip: [u16; 128] = [0; 128];
+    for (i, c) in wide.into_iter().take(127).enumerate() {
+        tip[i] = c;
+    }
+    tip
+}
+
+/// 꺼져 있으면 회색 아이콘, 켜져 있으면 마지막으로 알아낸 한/영 상태 ("한"/"A").
+/// 아직 상태를 모르면 기본 아이콘
+fn get_status_icon() -> HICON {
+    let enabled = engine().enabled();
+    let index = match IME_MODE.load(Ordering::SeqCst) {
+        _ if !enabled => TRAY_ICON_DISABLED,
+        MODE_HANGUL => TRAY_ICON_HANGUL,
+        MODE_ENGLISH => TRAY_ICON_ENGLISH,
+        _ => return unsafe { LoadIconW(None, IDI_APPLICATION).unwrap_or_default() },
+    };
+    let raw = TRAY_ICONS[index].load(Ordering::SeqCst);
+    if raw != 0 {
+        return HICON(raw as isize as *mut _);
+    }
+    let icon = unsafe {
+        match index {
+            TRAY_ICON_HANGUL => create_badge_icon(Badge::Hangul),
+            TRAY_ICON_ENGLISH => create_badge_icon(Badge::English),
+            _ => create_badge_icon(Badge::Disabled),
+        }
+    };
+    TRAY_ICONS[index].store(icon.0 as usize as u32, Ordering::SeqCst);
+    icon
+}
+
+/// 알아낸 한/영 상태를 기록하고, 바뀌었으면 트레이 아이콘을 갱신하게 한다.
+/// 훅 안에서도 불리므로 아이콘은 메인 윈도우가 메시지를 받아 바꾼다.
+fn set_ime_mode(native: bool) {
+    let mode = if native { MODE_HANGUL } else { MODE_ENGLISH };
+    if IME_MODE.swap(mode, Ordering::SeqCst) == mode {
+        return;
+    }
+    let hwnd_val = MAIN_HWND.load(Ordering::SeqCst);
+    if hwnd_val != 0 {
+        unsafe {
+            let hwnd = HWND(hwnd_val as isize as *mut _);
+            let _ = PostMessageW(hwnd, WM_IME_MODE, WPARAM(0), LPARAM(0));
+        }
+    }
+}
+
+fn destroy_tray_icons() {
+    for icon in &TRAY_ICONS {
+        let raw = icon.swap(0, Ordering::SeqCst);
+        if raw != 0 {
+            unsafe {
+                let _ = DestroyIcon(HICON(raw as isize as *mut _));
+            }
+        }
+    }
+}
+
+/// 트레이 아이콘과 표시기에 그리는 배지
+#[derive(Clone, Copy)]
+enum Badge {
+    Hangul,
+    English,
+    Disabled,
+}
+
+impl Badge {
+    fn of(native: bool) -> Self {
+        if native {
+            Badge::Hangul
+        } else {
+            Badge::English
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Badge::Hangul | Badge::Disabled => "한",
+            Badge::English => "A",
+        }
+    }
+
+    /// (바탕색, 글자색). COLORREF는 0x00BBGGRR
+    fn colors(self) -> (u32, u32) {
+        match self {
+            Badge::Hangul => (0x00B05A1E, 0x00FFFFFF),
+            Badge::English => (0x00505050, 0x00FFFFFF),
+            Badge::Disabled => (0x00D0D0D0, 0x00909090),
+        }
+    }
+}
+
+/// `rect`를 배지 색으로 칠하고 가운데에 글자를 쓴다
+unsafe fn draw_badge(hdc: HDC, rect: &RECT, badge: Badge) {
+    let (background, foreground) = badge.colors();
+    let brush = CreateSolidBrush(COLORREF(background));
+    FillRect(hdc, rect, brush);
+    let _ = DeleteObject(brush);
+
+    let face = wide_string("Malgun Gothic");
+    let height = (rect.bottom - rect.top) * 2 / 3;
+    let font = CreateFontW(
+        -height,
+        0,
+        0,
+        0,
+        FW_BOLD.0 as i32,
+        0,
+        0,
+        0,
+        DEFAULT_CHARSET.0 as u32,
+        OUT_DEFAULT_PRECIS.0 as u32,
+        CLIP_DEFAULT_PRECIS.0 as u32,
+        CLEARTYPE_QUALITY.0 as u32,
+        0,
+        wptr(&face),
+    );
+    let old_font = SelectObject(hdc, font);
+    SetBkMode(hdc, TRANSPARENT);
+    SetTextColor(hdc, COLORREF(foreground));
+    let mut text: Vec<u16> = badge.label().encode_utf16().collect();
+    let mut rect = *rect;
+    DrawTextW(
+        hdc,
+        &mut text,
+        &mut rect,
+        DT_CENTER | DT_VCENTER | DT_SINGLELINE,
+    );
+    SelectObject(hdc, old_font);
+    let _ = DeleteObject(font);
+}
+
+/// 작은 아이콘 크기(`SM_CXSMICON`)의 배지 아이콘을 그려서 만든다
+unsafe fn create_badge_icon(badge: Badge) -> HICON {
+    let size = GetSystemMetrics(SM_CXSMICON).max(16);
+    let screen = GetDC(None);
+    let dc = CreateCompatibleDC(screen);
+    let color = CreateCompatibleBitmap(screen, size, size);
+    // 마스크가 모두 0이면 아이콘 전체가 불투명
+    let mask_bits = vec![0u8; ((size + 15) / 16 * 2 * size) as usize];
+    let mask = CreateBitmap(size, size, 1, 1, Some(mask_bits.as_ptr().cast()));
+
+    let old = SelectObject(dc, color);
+    let rect = RECT {
+        left: 0,
+        top: 0,
+        right: size,
+        bottom: size,
+    };
+    draw_badge(dc, &rect, badge);
+    SelectObject(dc, old);
+
+    let info = ICONINFO {
+        fIcon: true.into(),
+        xHotspot: 0,
+        yHotspot: 0,
+        hbmMask: mask,
+        hbmColor: color,
+    };
+    let icon = CreateIconIndirect(&info).unwrap_or_default();
+    let _ = DeleteObject(color);
+    let _ = DeleteObject(mask);
+    let _ = DeleteDC(dc);
+    ReleaseDC(None, screen);
+    icon
+}
+
+fn add_tray_icon(hwnd: HWND) {
+    unsafe {
+        let mut nid: NOTIFYICONDATAW = zeroed();
+        nid.cbSize = size_of::<NOTIFYICONDATAW>() as u32;
+        nid.hWnd = hwnd;
+        nid.uID = TRAY_ICON_ID;
+        nid.uFlags = NIF_ICON | NIF_MESSAGE | NIF_TIP;
+        nid.uCallbackMessage = WM_TRAYICON;
+        nid.hIcon = get_status_icon();
+        nid.szTip = make_tooltip();
+        let _ = Shell_NotifyIconW(NIM_ADD, &nid);
+    }
+}
+
+fn update_tray_icon(hwnd: HWND) {
+    unsafe {
+        let mut nid: NOTIFYICONDATAW = zeroed();
+        nid.cbSize = size_of::<NOTIFYICONDATAW>() as u32;
+        nid.hWnd = hwnd;
+        nid.uID = TRAY_ICON_ID;
+        nid.uFlags = NIF_ICON | NIF_TIP;
+        nid.hIcon = get_status_icon();
+        nid.szTip = make_tooltip();
+        let _ = Shell_NotifyIconW(NIM_MODIFY, &nid);
+    }
+}
+
+/// 트레이 풍선 알림
+fn show_balloon(hwnd: HWND, title: &str, text: &str, error: bool) {
+    unsafe {
+        let mut nid: NOTIFYICONDATAW = zeroed();
+        nid.cbSize = size_of::<NOTIFYICONDATAW>() as u32;
+        nid.hWnd = hwnd;
+        nid.uID = TRAY_ICON_ID;
+        nid.uFlags = NIF_INFO;
+        nid.dwInfoFlags = if error { NIIF_ERROR } else { NIIF_WARNING };
+        for (dst, src) in nid
+            .szInfoTitle
+            .iter_mut()
+            .zip(title.encode_utf16().take(63))
+        {
+            *dst = src;
+        }
+        for (dst, src) in nid.szInfo.iter_mut().zip(text.encode_utf16().take(255)) {
+            *dst = src;
+        }
+        let _ = Shell_NotifyIconW(NIM_MODIFY, &nid);
+    }
+}
+
+/// 보관해 둔 설정 문제를 풍선으로 알린다
+fn show_config_notice(hwnd: HWND) {
+    let notice = CONFIG_NOTICE.lock().ok().and_then(|mut n| n.take());
+    if let Some((summary, error)) = notice {
+        let title = if error {
+            "설정 파일 오류"
+        } else {
+            "설정 파일 경고"
+        };
+        show_balloon(hwnd, title, &summary, error);
+    }
+}
+
+fn remove_tray_icon(hwnd: HWND) {
+    unsafe {
+        let mut nid: NOTIFYICONDATAW = zeroed();
+        nid.cbSize = size_of::<NOTIFYICONDATAW>() as u32;
+        nid.hWnd = hwnd;
+        nid.uID = TRAY_ICON_ID;
+        let _ = Shell_NotifyIconW(NIM_DELETE, &nid);
+    }
+}
+
+// ── 키 감지 팝업 ──────────────────────────────────────────────────────────
+
+fn show_detect_popup(hinstance: HINSTANCE, action: Action) {
+    unsafe {
+        // 이미 열려 있으면 무시
+        let existing = DETECT_HWND.load(Ordering::SeqCst);
+        if existing != 0 {
+            return;
+        }
+
+        let class_name = wide_string("synergy_hangul_fix_detect");
+        let wc = WNDCLASSW {
+            lpfnWndProc: Some(detect_wndproc),
+            hInstance: hinstance,
+            lpszClassName: wptr(&class_name),
+            hbrBackground: windows::Win32::Graphics::Gdi::HBRUSH(
+                windows::Win32::Graphics::Gdi::GetStockObject(
+                    windows::Win32::Graphics::Gdi::WHITE_BRUSH,
+                )
+                .0,
+            ),
+            ..Default::default()
+        };
+        RegisterClassW(&wc);
+
+        let title = wide_string("키 감지");
+        let hwnd = CreateWindowExW(
+            WS_EX_TOPMOST,
+            wptr(&class_name),
+            wptr(&title),
+            WINDOW_STYLE(WS_OVERLAPPED.0 | WS_CAPTION.0 | WS_SYSMENU.0),
+            CW_USEDEFAULT,
+            CW_USEDEFAULT,
+            350,
+            150,
+            None,
+            None,
+            Some(&hinstance),
+            None,
+        )
+        .unwrap();
+
+        DETECT_HWND.store(hwnd.0 as usize as u32, Ordering::SeqCst);
+
+        // STATIC 컨트롤 (안내 텍스트)
+        let label_class = wide_string("STATIC");
+        let label_text = wide_string(&format!(
+            "감지 중...\n\n{} 트리거로 사용할 키(또는 Ctrl/Shift/Alt/Win 조합)를 눌러주세요.",
+            action.label()
+        ));
+        let _label = CreateWindowExW(
+            WINDOW_EX_STYLE::default(),
+            wptr(&label_class),
+            wptr(&label_text),
+            WINDOW_STYLE(WS_CHILD.0 | WS_VISIBLE.0 | 0x01), // SS_CENTER = 0x01
+            20,
+            20,
+            300,
+            80,
+            hwnd,
+            None,
+            Some(&hinstance),
+            None,
+        );
+
+        let _ = ShowWindow(hwnd, SW_SHOW);
+        let _ = SetForegroundWindow(hwnd);
+    }
+}
+
+fn close_detect_popup() {
+    let hwnd_val = DETECT_HWND.load(Ordering::SeqCst);
+    if hwnd_val != 0 {
+        unsafe {
+            let _ = DestroyWindow(HWND(hwnd_val as isize as *mut _));
+        }
+        DETECT_HWND.store(0, Ordering::SeqCst);
+    }
+}
+
+unsafe extern "system" fn detect_wndproc(
+    hwnd: HWND,
+    msg: u32,
+    w_param: WPARAM,
+    l_param: LPARAM,
+) -> LRESULT {
+    match msg {
+        WM_CLOSE => {
+            // X 버튼으로 닫기 → 학습 취소
+            engine().cancel_learning();
+            let _ = DestroyWindow(hwnd);
+            DETECT_HWND.store(0, Ordering::SeqCst);
+            debug_log("[LEARN] cancelled by user");
+            LRESULT(0)
+        }
+        WM_DESTROY => {
+            DETECT_HWND.store(0, Ordering::SeqCst);
+            LRESULT(0)
+        }
+        _ => DefWindowProcW(hwnd, msg, w_param, l_param),
+    }
+}
+
+/// 키 감지 후 확인 다이얼로그
+fn confirm_learned_key(hwnd: HWND, trigger: Trigger) {
+    close_detect_popup();
+
+    let action = LEARN_ACTION
+        .lock()
+        .map(|a| *a)
+        .unwrap_or(Action::ToggleHangul);
+    let key_name = trigger_display(trigger);
+    let msg_text = wide_string(&format!(
+        "감지된 키: {} ({})\n\n이 키를 {} 트리거로 추가하시겠습니까?",
+        key_name,
+        trigger,
+        action.label()
+    ));
+    let msg_title = wide_string("키 감지 완료");
+
+    let confirmed = !config().ui.confirm_learned_key
+        || unsafe {
+            MessageBoxW(
+                hwnd,
+                wptr(&msg_text),
+                wptr(&msg_title),
+                MB_YESNO | MB_ICONQUESTION,
+            )
+        }
+        .0 == 6; // IDYES = 6
+
+    if confirmed {
+        let binding = Binding::new(trigger, action);
+        engine().bind(binding);
+        update_tray_icon(hwnd);
+        save_config();
+        debug_log(&format!(
+            "[CONFIG] trigger added → {} ({}) confirmed & saved",
+            binding_display(binding),
+            binding
+        ));
+    } else {
+        debug_log("[LEARN] user declined the detected key");
+    }
+}
+
+// ── 디버그 윈도우 ─────────────────────────────────────────────────────────
+
+fn create_debug_window(hinstance: HINSTANCE) {
+    unsafe {
+        let class_name = wide_string("synergy_hangul_fix_debug");
+        let wc = WNDCLASSW {
+            lpfnWndProc: Some(debug_wndproc),
+            hInstance: hinstance,
+            lpszClassName: wptr(&class_name),
+            ..Default::default()
+        };
+        RegisterClassW(&wc);
+
+        let title = wide_string(DEBUG_TITLE);
+        let hwnd = CreateWindowExW(
+            WINDOW_EX_STYLE::default(),
+            wptr(&class_name),
+            wptr(&title),
+            WS_OVERLAPPEDWINDOW,
+            CW_USEDEFAULT,
+            CW_USEDEFAULT,
+            600,
+            400,
+            None,
+            None,
+            Some(&hinstance),
+            None,
+        )
+        .unwrap();
+
+        DEBUG_HWND.store(hwnd.0 as usize as u32, Ordering::SeqCst);
+
+        let edit_class = wide_string("EDIT");
+        let edit_hwnd = CreateWindowExW(
+            WINDOW_EX_STYLE(0x200),
+            wptr(&edit_class),
+            PCWSTR::null(),
+            WINDOW_STYLE(
+                WS_CHILD.0
+                    | WS_VISIBLE.0
+                    | WS_VSCROLL.0
+                    | WS_HSCROLL.0
+                    | ES_MULTILINE as u32
+                    | ES_READONLY as u32
+                    | ES_AUTOVSCROLL as u32
+                    | ES_AUTOHSCROLL as u32,
+            ),
+            0,
+            0,
+            600,
+            400,
+            hwnd,
+            HMENU(IDC_DEBUG_EDIT as isize as *mut _),
+            Some(&hinstance),
+            None,
+        )
+        .unwrap();
+
+        DEBUG_EDIT_HWND.store(edit_hwnd.0 as usize as u32, Ordering::SeqCst);
+    }
+}
+
+fn toggle_debug_window() {
+    let hwnd_val = DEBUG_HWND.load(Ordering::SeqCst);
+    if hwnd_val == 0 {
+        return;
+    }
+    unsafe {
+        let hwnd = HWND(hwnd_val as isize as *mut _);
+        let visible = DEBUG_VISIBLE.load(Ordering::SeqCst);
+        if visible {
+            let _ = ShowWindow(hwnd, SW_HIDE);
+            DEBUG_VISIBLE.store(false, Ordering::SeqCst);
+        } else {
+            let _ = ShowWindow(hwnd, SW_SHOW);
+            let _ = SetForegroundWindow(hwnd);
+            DEBUG_VISIBLE.store(true, Ordering::SeqCst);
+        }
+    }
+}
+
+unsafe extern "system" fn debug_wndproc(
+    hwnd: HWND,
+    msg: u32,
+    w_param: WPARAM,
+    l_param: LPARAM,
+) -> LRESULT {
+    match msg {
+        WM_SIZE => {
+            let width = (l_param.0 & 0xFFFF) as i32;
+            let height = ((l_param.0 >> 16) & 0xFFFF) as i32;
+            let edit_val = DEBUG_EDIT_HWND.load(Ordering::SeqCst);
+            if edit_val != 0 {
+                let edit_hwnd = HWND(edit_val as isize as *mut _);
+                let _ = MoveWindow(edit_hwnd, 0, 0, width, height, true);
+            }
+            LRESULT(0)
+        }
+        WM_CLOSE => {
+            let _ = ShowWindow(hwnd, SW_HIDE);
+            DEBUG_VISIBLE.store(false, Ordering::SeqCst);
+            LRESULT(0)
+        }
+        _ => DefWindowProcW(hwnd, msg, w_param, l_param),
+    }
+}
+
+// ── 한/영 표시기 ──────────────────────────────────────────────────────────
+
+/// 클릭이 통과하는 최상위 창. 숨겨 두었다가 전환할 때마다 잠깐 보여 준다.
+fn create_indicator_window(hinstance: HINSTANCE) {
+    unsafe {
+        let class_name = wide_string("synergy_hangul_fix_indicator");
+        let wc = WNDCLASSW {
+            lpfnWndProc: Some(indicator_wndproc),
+            hInstance: hinstance,
+            lpszClassName: wptr(&class_name),
+            ..Default::default()
+        };
+        RegisterClassW(&wc);
+
+        let Ok(hwnd) = CreateWindowExW(
+            WS_EX_LAYERED | WS_EX_TRANSPARENT | WS_EX_TOPMOST | WS_EX_TOOLWINDOW | WS_EX_NOACTIVATE,
+            wptr(&class_name),
+            PCWSTR::null(),
+            WS_POPUP,
+            0,
+            0,
+            INDICATOR_SIZE,
+            INDICATOR_SIZE,
+            None,
+            None,
+            Some(&hinstance),
+            None,
+        ) else {
+            debug_log("[INDICATOR] window creation failed");
+            return;
+        };
+        let _ = SetLayeredWindowAttributes(hwnd, COLORREF(0), 230, LWA_ALPHA);
+        INDICATOR_HWND.store(hwnd.0 as usize as u32, Ordering::SeqCst);
+    }
+}
+
+/// 캐럿(없으면 마우스 커서) 옆에 "한" 또는 "A"를 `duration_ms` 동안 띄운다
+fn show_indicator(native: bool) {
+    let duration = INDICATOR_MS.load(Ordering::SeqCst);
+    let hwnd_val = INDICATOR_HWND.load(Ordering::SeqCst);
+    if duration == 0 || hwnd_val == 0 {
+        return;
+    }
+    INDICATOR_NATIVE.store(native, Ordering::SeqCst);
+    unsafe {
+        let hwnd = HWND(hwnd_val as isize as *mut _);
+        let pos = indicator_position();
+        let _ = SetWindowPos(
+            hwnd,
+            HWND_TOPMOST,
+            pos.x,
+            pos.y,
+            INDICATOR_SIZE,
+            INDICATOR_SIZE,
+            SWP_NOACTIVATE | SWP_SHOWWINDOW,
+        );
+        let _ = InvalidateRect(hwnd, None, true);
+        SetTimer(hwnd, TIMER_INDICATOR, duration, None);
+    }
+}
+
+/// 포그라운드 스레드의 캐럿 아래, 캐럿이 없으면 마우스 커서 오른쪽 아래
+unsafe fn indicator_position() -> POINT {
+    let fg_hwnd = GetForegroundWindow();
+    if !fg_hwnd.0.is_null() {
+        let thread = GetWindowThreadProcessId(fg_hwnd, None);
+        let mut info = GUITHREADINFO {
+            cbSize: size_of::<GUITHREADINFO>() as u32,
+            ..Default::default()
+        };
+        if GetGUIThreadInfo(thread, &mut info).is_ok() && !info.hwndCaret.0.is_null() {
+            let mut pt = POINT {
+                x: info.rcCaret.left,
+                y: info.rcCaret.bottom,
+            };
+            if ClientToScreen(info.hwndCaret, &mut pt).as_bool() {
+                return POINT {
+                    x: pt.x,
+                    y: pt.y + 4,
+                };
+            }
+        }
+    }
+    let mut pt = POINT::default();
+    let _ = GetCursorPos(&mut pt);
+    POINT {
+        x: pt.x + 16,
+        y: pt.y + 16,
+    }
+}
+
+unsafe extern "system" fn indicator_wndproc(
+    hwnd: HWND,
+    msg: u32,
+    w_param: WPARAM,
+    l_param: LPARAM,
+) -> LRESULT {
+    match msg {
+        WM_PAINT => {
+            let native = INDICATOR_NATIVE.load(Ordering::SeqCst);
+            let mut ps = PAINTSTRUCT::default();
+            let hdc = BeginPaint(hwnd, &mut ps);
+            let mut rect = RECT::default();
+            let _ = GetClientRect(hwnd, &mut rect);
+            draw_badge(hdc, &rect, Badge::of(native));
+            let _ = EndPaint(hwnd, &ps);
+            LRESULT(0)
+        }
+        WM_TIMER if w_param.0 == TIMER_INDICATOR => {
+            let _ = KillTimer(hwnd, TIMER_INDICATOR);
+            let _ = ShowWindow(hwnd, SW_HIDE);
+            LRESULT(0)
+        }
+        WM_NCHITTEST => LRESULT(HTTRANSPARENT as isize),
+        _ => DefWindowProcW(hwnd, msg, w_param, l_param),
+    }
+}
+
+// ── 컨텍스트 메뉴 ────────────────────────────────────────────────────────
+
+fn show_context_menu(hwnd: HWND) {
+    unsafe {
+        let menu = CreatePopupMenu().unwrap();
+        let (enabled, bindings, mode) = {
+            let engine = engine();
+            (engine.enabled(), engine.bindings().to_vec(), engine.mode())
+        };
+        let bound = |vk: VIRTUAL_KEY| {
+            let trigger = Trigger::single(vk.0 as u32);
+            bindings.iter().any(|b| b.trigger == trigger)
+        };
+
+        let toggle_text = if enabled {
+            wide_string("비활성화(&D)")
+        } else {
+            wide_string("활성화(&E)")
+        };
+        AppendMenuW(menu, MF_STRING, IDM_TOGGLE as usize, wptr(&toggle_text)).ok();
+        AppendMenuW(menu, MF_SEPARATOR, 0, PCWSTR::null()).ok();
+
+        // 프로필 서브메뉴: 기본 프로필 + `[[profiles]]`
+        let profile_menu = CreatePopupMenu().unwrap();
+        let config = config();
+        let active = config.active_profile();
+        let names =
+            std::iter::once(None).chain(config.profiles.iter().map(|p| Some(p.name.as_str())));
+        for (i, name) in names.take(MAX_PROFILE_ITEMS as usize).enumerate() {
+            let flags = MF_STRING
+                | if name == active {
+                    MF_CHECKED
+                } else {
+                    MF_UNCHECKED
+                };
+            let profile_text = wide_string(name.unwrap_or(DEFAULT_PROFILE));
+            AppendMenuW(
+                profile_menu,
+                flags,
+                (IDM_PROFILE_FIRST + i as u32) as usize,
+                wptr(&profile_text),
+            )
+            .ok();
+        }
+        if config.profiles.is_empty() {
+            let hint_text = wide_string("(config.toml의 [[profiles]]에서 추가)");
+            AppendMenuW(profile_menu, MF_STRING | MF_GRAYED, 0, wptr(&hint_text)).ok();
+        }
+        let profile_menu_text = wide_string("프로필(&P)");
+        AppendMenuW(
+            menu,
+            MF_STRING | MF_POPUP,
+            profile_menu.0 as usize,
+            wptr(&profile_menu_text),
+        )
+        .ok();
+
+        // 트리거 키 서브메뉴: 프리셋은 켜고 끄는 체크 항목
+        let submenu = CreatePopupMenu().unwrap();
+        let caps_flags = MF_STRING
+            | if bound(VK_CAPITAL) {
+                MF_CHECKED
+            } else {
+                MF_UNCHECKED
+            };
+        let f13_flags = MF_STRING
+            | if bound(VK_F13) {
+                MF_CHECKED
+            } else {
+                MF_UNCHECKED
+            };
+        let ralt_flags = MF_STRING
+            | if bound(VK_RMENU) {
+                MF_CHECKED
+            } else {
+                MF_UNCHECKED
+            };
+
+        AppendMenuW(
+            submenu,
+            caps_flags,
+            IDM_KEY_CAPSLOCK as usize,
+            wptr(&wide_string(&KeyName(VK_CAPITAL.0 as u32).to_string())),
+        )
+        .ok();
+        AppendMenuW(
+            submenu,
+            f13_flags,
+            IDM_KEY_F13 as usize,
+            wptr(&wide_string(&KeyName(VK_F13.0 as u32).to_string())),
+        )
+        .ok();
+        AppendMenuW(
+            submenu,
+            ralt_flags,
+            IDM_KEY_RALT as usize,
+            wptr(&wide_string(&KeyName(VK_RMENU.0 as u32).to_string())),
+        )
+        .ok();
+
+        // 트리거 테이블: 항목마다 동작 선택 + 삭제 서브메뉴
+        AppendMenuW(submenu, MF_SEPARATOR, 0, PCWSTR::null()).ok();
+        if bindings.is_empty() {
+            let empty_text = wide_string("(트리거 없음)");
+            AppendMenuW(submenu, MF_STRING | MF_GRAYED, 0, wptr(&empty_text)).ok();
+        }
+        for (i, binding) in bindings.iter().take(MAX_BINDING_ITEMS as usize).enumerate() {
+            let base = IDM_BINDING_FIRST + i as u32 * IDM_BINDING_STRIDE;
+            let binding_menu = CreatePopupMenu().unwrap();
+            for (j, action) in Action::ALL.into_iter().enumerate() {
+                let flags = MF_STRING
+                    | if binding.action == action {
+                        MF_CHECKED
+                    } else {
+                        MF_UNCHECKED
+                    };
+                let action_text = wide_string(action.label());
+                AppendMenuW(
+                    binding_menu,
+                    flags,
+                    (base + j as u32) as usize,
+                    wptr(&action_text),
+                )
+                .ok();
+            }
+            AppendMenuW(binding_menu, MF_SEPARATOR, 0, PCWSTR::null()).ok();
+            for (j, source) in Source::ALL.into_iter().enumerate() {
+                let flags = MF_STRING
+                    | if binding.source == source {
+                        MF_CHECKED
+                    } else {
+                        MF_UNCHECKED
+                    };
+                let source_text = wide_string(source.label());
+                let id = base + IDM_BINDING_SOURCE + j as u32;
+                AppendMenuW(binding_menu, flags, id as usize, wptr(&source_text)).ok();
+            }
+            AppendMenuW(binding_menu, MF_SEPARATOR, 0, PCWSTR::null()).ok();
+            let remove_text = wide_string("삭제(&R)");
+            AppendMenuW(
+                binding_menu,
+                MF_STRING,
+                (base + IDM_BINDING_REMOVE) as usize,
+                wptr(&remove_text),
+            )
+            .ok();
+
+            let item_text = wide_string(&format!(
+                "{} ({})",
+                binding_display(*binding),
+                binding.trigger
+            ));
+            AppendMenuW(
+                submenu,
+                MF_STRING | MF_POPUP,
+                binding_menu.0 as usize,
+                wptr(&item_text),
+            )
+            .ok();
+        }
+
+        AppendMenuW(submenu, MF_SEPARATOR, 0, PCWSTR::null()).ok();
+        let tap_flags = MF_STRING
+            | if mode == TriggerMode::Tap {
+                MF_CHECKED
+            } else {
+                MF_UNCHECKED
+            };
+        let tap_text = wide_string("탭/홀드 구분(&T)");
+        AppendMenuW(submenu, tap_flags, IDM_TAP_HOLD as usize, wptr(&tap_text)).ok();
+        let learn_menu = CreatePopupMenu().unwrap();
+        for (i, action) in Action::ALL.into_iter().enumerate() {
+            let action_text = wide_string(&format!("{}...", action.label()));
+            AppendMenuW(
+                learn_menu,
+                MF_STRING,
+                (IDM_KEY_LEARN_FIRST + i as u32) as usize,
+                wptr(&action_text),
+            )
+            .ok();
+        }
+        let learn_text = wide_string("키 감지(&L)");
+        AppendMenuW(
+            submenu,
+            MF_STRING | MF_POPUP,
+            learn_menu.0 as usize,
+            wptr(&learn_text),
+        )
+        .ok();
+
+        let key_menu_text = wide_string("트리거 키(&K)");
+        AppendMenuW(menu, MF_STRING | MF_POPUP, submenu.0 as usize, wptr(&key_menu_text)).ok();
+
+        let release_text = wide_string("수식 키 모두 떼기(&R)");
+        AppendMenuW(
+            menu,
+            MF_STRING,
+            IDM_RELEASE_MODIFIERS as usize,
+            wptr(&release_text),
+        )
+        .ok();
+
+        AppendMenuW(menu, MF_SEPARATOR, 0, PCWSTR::null()).ok();
+
+        let debug_text = if DEBUG_VISIBLE.load(Ordering::SeqCst) {
+            wide_string("디버그 닫기(&B)")
+        } else {
+            wide_string("디버그(&B)")
+        };
+        AppendMenuW(menu, MF_STRING, IDM_DEBUG as usize, wptr(&debug_text)).ok();
+
+        AppendMenuW(menu, MF_SEPARATOR, 0, PCWSTR::null()).ok();
+        let exit_text = wide_string("종료(&X)");
+        AppendMenuW(menu, MF_STRING, IDM_EXIT as usize, wptr(&exit_text)).ok();
+
+        let mut pt = POINT::default();
+        GetCursorPos(&mut pt).ok();
+        let _ = SetForegroundWindow(hwnd);
+        let _ = TrackPopupMenu(
+            menu,
+            TPM_RIGHTALIGN | TPM_BOTTOMALIGN,
+            pt.x,
+            pt.y,
+            0,
+            hwnd,
+            None,
+        );
+        PostMessageW(hwnd, WM_NULL, WPARAM(0), LPARAM(0)).ok();
+        let _ = DestroyMenu(menu);
+    }
+}
+
+/// 트리거 전체 켜기/끄기 (설정 파일에도 저장)
+fn toggle_enabled(hwnd: HWND) {
+    let enabled = engine().toggle_enabled();
+    update_tray_icon(hwnd);
+    save_config();
+    let state = if enabled { "ON" } else { "OFF" };
+    debug_log(&format!("[STATE] enabled toggled → {}", state));
+}
+
+/// 프로필 전환 (0은 기본 프로필). 지금 트리거는 떠나는 프로필에 남긴다.
+fn select_profile(hwnd: HWND, index: usize) {
+    let name = {
+        let mut guard = CONFIG.lock().unwrap_or_else(PoisonError::into_inner);
+        let config = guard.get_or_insert_with(Config::default);
+        let name = match index.checked_sub(1) {
+            None => None,
+            Some(i) => match config.profiles.get(i) {
+                Some(profile) => Some(profile.name.clone()),
+                None => return,
+            },
+        };
+        let mut engine = engine();
+        config.capture(&engine);
+        config.select_profile(name.as_deref());
+        engine.set_bindings(config.bindings());
+        name
+    };
+    update_tray_icon(hwnd);
+    save_config();
+    debug_log(&format!(
+        "[PROFILE] switched → {}",
+        name.as_deref().unwrap_or(DEFAULT_PROFILE)
+    ));
+    let bindings = engine().bindings().to_vec();
+    for binding in bindings {
+        debug_log(&format!(
+            "[PROFILE]   {} ({})",
+            binding,
+            binding_display(binding)
+        ));
+    }
+}
+
+/// 프리셋 키를 한/영 전환 트리거로 추가하거나, 이미 있으면 뺀다
+fn toggle_preset(hwnd: HWND, vk: VIRTUAL_KEY) {
+    let trigger = Trigger::single(vk.0 as u32);
+    let removed = {
+        let mut engine = engine();
+        let removed = engine.unbind(trigger);
+        if !removed {
+            engine.bind(Binding::new(trigger, Action::ToggleHangul));
+        }
+        removed
+    };
+    update_tray_icon(hwnd);
+    save_config();
+    let change = if removed { "removed" } else { "added" };
+    debug_log(&format!(
+        "[CONFIG] trigger {} → {} ({})",
+        change,
+        trigger_display(trigger),
+        trigger
+    ));
+}
+
+/// 트리거 테이블 항목의 동작 변경
+fn set_binding_action(hwnd: HWND, index: usize, action: Action) {
+    let Some(binding) = engine().bindings().get(index).copied() else {
+        return;
+    };
+    let binding = Binding { action, ..binding };
+    engine().bind(binding);
+    update_tray_icon(hwnd);
+    save_config();
+    debug_log(&format!("[CONFIG] trigger action → {}", binding));
+}
+
+/// 트리거 테이블 항목이 반응할 입력 출처 변경
+fn set_binding_source(hwnd: HWND, index: usize, source: Source) {
+    let binding = {
+        let mut engine = engine();
+        let mut bindings = engine.bindings().to_vec();
+        let Some(binding) = bindings.get_mut(index) else {
+            return;
+        };
+        binding.source = source;
+        let binding = *binding;
+        engine.set_bindings(bindings);
+        binding
+    };
+    update_tray_icon(hwnd);
+    save_config();
+    debug_log(&format!("[CONFIG] trigger source → {}", binding));
+}
+
+/// 트리거 테이블 항목 삭제 (확인 후)
+fn remove_binding(hwnd: HWND, index: usize) {
+    let Some(binding) = engine().bindings().get(index).copied() else {
+        return;
+    };
+
+    let msg_text = wide_string(&format!(
+        "{}\n\n이 트리거를 삭제하시겠습니까?",
+        binding_display(binding)
+    ));
+    let msg_title = wide_string("트리거 삭제");
+    let confirmed = !config().ui.confirm_remove
+        || unsafe {
+            MessageBoxW(
+                hwnd,
+                wptr(&msg_text),
+                wptr(&msg_title),
+                MB_YESNO | MB_ICONQUESTION,
+            )
+        }
+        .0 == 6; // IDYES = 6
+
+    if confirmed {
+        engine().remove_binding(binding);
+        update_tray_icon(hwnd);
+        save_config();
+        debug_log(&format!("[CONFIG] trigger removed → {}", binding));
+    }
+}
+
+// ── 윈도우 프로시저 ───────────────────────────────────────────────────────
+
+/// HINSTANCE 저장 (detect popup 생성에 필요)
+static HINSTANCE_VAL: AtomicU32 = AtomicU32::new(0);
+
+unsafe extern "system" fn wndproc(
+    hwnd: HWND,
+    msg: u32,
+    w_param: WPARAM,
+    l_param: LPARAM,
+) -> LRESULT {
+    match msg {
+        WM_TRAYICON => {
+            let event = (l_param.0 & 0xFFFF) as u32;
+            match event {
+                WM_LBUTTONUP => toggle_enabled(hwnd),
+                WM_RBUTTONUP => {
+                    show_context_menu(hwnd);
+                }
+                _ => {}
+            }
+            LRESULT(0)
+        }
+
+        WM_TIMER if w_param.0 == TIMER_CONFIG_WATCH => {
+            poll_config_file(hwnd);
+            LRESULT(0)
+        }
+
+        WM_TIMER if w_param.0 == TIMER_CAPS => {
+            restore_caps_lock(hwnd);
+            LRESULT(0)
+        }
+
+        WM_TIMER if w_param.0 == TIMER_VERIFY => {
+            verify_ime_action(hwnd);
+            LRESULT(0)
+        }
+
+        WM_DEBUG_LOG => {
+            flush_debug_log();
+            LRESULT(0)
+        }
+
+        WM_IME_MODE => {
+            update_tray_icon(hwnd);
+            LRESULT(0)
+        }
+
+        WM_FOREGROUND => {
+            on_foreground(hwnd, HWND(w_param.0 as *mut _));
+            LRESULT(0)
+        }
+
+        WM_IME_ACTION => {
+            let action = Action::ALL.get(w_param.0).copied();
+            let method = Method::ALL.get(l_param.0 as usize).copied();
+            if let (Some(action), Some(method)) = (action, method) {
+                run_action(hwnd, action, method);
+            }
+            LRESULT(0)
+        }
+
+        WM_KEY_LEARNED => {
+            let learned = LEARNED_TRIGGER.lock().ok().and_then(|mut t| t.take());
+            if let Some(trigger) = learned {
+                confirm_learned_key(hwnd, trigger);
+            }
+            LRESULT(0)
+        }
+
+        WM_COMMAND => {
+            let cmd = (w_param.0 & 0xFFFF) as u32;
+            match cmd {
+                IDM_TOGGLE => toggle_enabled(hwnd),
+                IDM_KEY_CAPSLOCK => toggle_preset(hwnd, VK_CAPITAL),
+                IDM_KEY_F13 => toggle_preset(hwnd, VK_F13),
+                IDM_KEY_RALT => toggle_preset(hwnd, VK_RMENU),
+                id if (IDM_PROFILE_FIRST..IDM_PROFILE_FIRST + MAX_PROFILE_ITEMS).contains(&id) => {
+                    select_profile(hwnd, (id - IDM_PROFILE_FIRST) as usize);
+                }
+                id if (IDM_BINDING_FIRST
+                    ..IDM_BINDING_FIRST + MAX_BINDING_ITEMS * IDM_BINDING_STRIDE)
+                    .contains(&id) =>
+                {
+                    let index = ((id - IDM_BINDING_FIRST) / IDM_BINDING_STRIDE) as usize;
+                    let item = (id - IDM_BINDING_FIRST) % IDM_BINDING_STRIDE;
+                    if item == IDM_BINDING_REMOVE {
+                        remove_binding(hwnd, index);
+                    } else if let Some(source) = item
+                        .checked_sub(IDM_BINDING_SOURCE)
+                        .and_then(|j| Source::ALL.get(j as usize))
+                    {
+                        set_binding_source(hwnd, index, *source);
+                    } else if let Some(&action) = Action::ALL.get(item as usize) {
+                        set_binding_action(hwnd, index, action);
+                    }
+                }
+                IDM_TAP_HOLD => {
+                    let mode = {
+                        let mut engine = engine();
+                        let mode = match engine.mode() {
+                            TriggerMode::Press => TriggerMode::Tap,
+                            TriggerMode::Tap => TriggerMode::Press,
+                        };
+                        engine.set_mode(mode);
+                        mode
+                    };
+                    update_tray_icon(hwnd);
+                    save_config();
+                    debug_log(&format!("[CONFIG] trigger mode → {:?}", mode));
+                }
+                id if (IDM_KEY_LEARN_FIRST..IDM_KEY_LEARN_FIRST + Action::ALL.len() as u32)
+                    .contains(&id) =>
+                {
+                    // 학습 모드 진입 + 감지 팝업
+                    let action = Action::ALL[(id - IDM_KEY_LEARN_FIRST) as usize];
+                    if let Ok(mut learn_action) = LEARN_ACTION.lock() {
+                        *learn_action = action;
+                    }
+                    engine().start_learning();
+                    debug_log(&format!("[LEARN] waiting for key press ({})...", action));
+                    let h = HINSTANCE_VAL.load(Ordering::SeqCst);
+                    if h != 0 {
+                        show_detect_popup(HINSTANCE(h as isize as *mut _), action);
+                    }
+                }
+                IDM_RELEASE_MODIFIERS => release_all_modifiers(),
+                IDM_DEBUG => {
+                    toggle_debug_window();
+                }
+                IDM_EXIT => {
+                    DestroyWindow(hwnd).ok();
+                }
+                _ => {}
+            }
+            LRESULT(0)
+        }
+
+        WM_DESTROY => {
+            remove_tray_icon(hwnd);
+            destroy_tray_icons();
+
+            let raw = HOOK_HANDLE.load(Ordering::SeqCst);
+            if raw != 0 {
+                let hook = HHOOK(raw as isize as *mut _);
+                let _ = UnhookWindowsHookEx(hook);
+            }
+            let raw = WIN_EVENT_HOOK.load(Ordering::SeqCst);
+            if raw != 0 {
+                let _ = UnhookWinEvent(HWINEVENTHOOK(raw as isize as *mut _));
+            }
+
+            close_detect_popup();
+
+            let dbg = DEBUG_HWND.load(Ordering::SeqCst);
+            if dbg != 0 {
+                let _ = DestroyWindow(HWND(dbg as isize as *mut _));
+            }
+
+            PostQuitMessage(0);
+            LRESULT(0)
+        }
+
+        _ => DefWindowProcW(hwnd, msg, w_param, l_param),
+    }
+}
+
+// ── 유틸리티 ──────────────────────────────────────────────────────────────
+
+fn wide_string(s: &str) -> Vec<u16> {
+    s.encode_utf16().chain(std::iter::once(0)).collect()
+}
+
+fn wptr(s: &[u16]) -> PCWSTR {
+    PCWSTR(s.as_ptr())
+}
+
+// ── 메인 ──────────────────────────────────────────────────────────────────
+
+/// `--check-config` 결과를 보여 줄 수 있게 실행한 콘솔에 붙는다 (GUI 서브시스템이라 기본은 콘솔 없음)
+pub fn attach_console() {
+    unsafe {
+        let _ = AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
+
+pub fn run(location: Location) {
+    unsafe {
+        let hmodule = GetModuleHandleW(None).unwrap();
+        let hinstance: HINSTANCE = hmodule.into();
+        HINSTANCE_VAL.store(hinstance.0 as usize as u32, Ordering::SeqCst);
+
+        // 설정 파일 로드
+        if let Ok(mut current) = CONFIG_LOCATION.lock() {
+            *current = Some(location);
+        }
+        load_config();
+
+        // 메인 히든 윈도우
+        let class_name = wide_string("synergy_hangul_fix_wnd");
+        let wc = WNDCLASSW {
+            lpfnWndProc: Some(wndproc),
+            hInstance: hinstance,
+            lpszClassName: wptr(&class_name),
+            ..Default::default()
+        };
+        RegisterClassW(&wc);
+
+        let hwnd = CreateWindowExW(
+            WINDOW_EX_STYLE::default(),
+            wptr(&class_name),
+            wptr(&wide_string("synergy-hangul-fix")),
+            WS_OVERLAPPED,
+            0,
+            0,
+            0,
+            0,
+            HWND_MESSAGE,
+            None,
+            Some(&hinstance),
+            None,
+        )
+        .unwrap();
+
+        MAIN_HWND.store(hwnd.0 as usize as u32, Ordering::SeqCst);
+        // Caps Lock 보존은 처음 상태를 알아야 시작할 수 있다
+        schedule_caps_check();
+
+        // 디버그 윈도우 (설정에 따라 처음부터 표시)
+        create_debug_window(hinstance);
+        if config().logging.debug_window {
+            toggle_debug_window();
+        }
+
+        // 한/영 표시기 (전환할 때까지 숨겨 둔다)
+        create_indicator_window(hinstance);
+
+        // 키보드 훅
+        let hook = SetWindowsHookExW(WH_KEYBOARD_LL, Some(keyboard_proc), None, 0);
+        match hook {
+            Ok(hook) => {
+                HOOK_HANDLE.store(hook.0 as usize as u32, Ordering::SeqCst);
+                debug_log("[INIT] keyboard hook installed OK");
+            }
+            Err(e) => {
+                MessageBoxW(
+                    None,
+                    wptr(&wide_string(
+                        "키보드 훅 설치에 실패했습니다.\n관리자 권한으로 실행해 주세요.",
+                    )),
+                    wptr(&wide_string("synergy-hangul-fix 오류")),
+                    MB_ICONERROR | MB_OK,
+                );
+                debug_log(&format!("[INIT] keyboard hook FAILED: {:?}", e));
+                return;
+            }
+        }
+
+        // 트레이 아이콘
+        add_tray_icon(hwnd);
+        show_config_notice(hwnd);
+        SetTimer(hwnd, TIMER_CONFIG_WATCH, CONFIG_WATCH_MS, None);
+        let bindings: Vec<String> = engine().bindings().iter().map(|b| b.to_string()).collect();
+        debug_log(&format!(
+            "[INIT] started | triggers=[{}] | enabled={}",
+            bindings.join("; "),
+            engine().enabled()
+        ));
+
+        // 메시지 루프
+        let mut msg = MSG::default();
+        while GetMessageW(&mut msg, None, 0, 0).as_bool() {
+            let _ = TranslateMessage(&msg);
+            DispatchMessageW(&msg);
+        }
+    }
+}
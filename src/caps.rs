@@ -0,0 +1,116 @@
+//! Caps Lock 상태 보존 (`[caps_lock] preserve`)
+//!
+//! Synergy는 중계하는 Caps Lock의 토글 상태(LED)를 먼저 바꿔 놓기도 해서, 트리거가
+//! 키를 가로채도 대문자 고정이 바뀔 수 있다. 트리거 누름 전의 상태를 알고 있다가
+//! 조금 뒤 다시 읽은 상태와 다르면 되돌린다.
+//! 토글 상태는 입력 큐(스레드)마다 따로라서 키보드 훅 안에서는 믿을 만한 값을 읽을 수
+//! 없다. 그래서 전역 상태는 호출하는 쪽이 메인 스레드에서 읽어 [`CapsGuard::check`]로
+//! 넘기고, 훅은 어떤 누름이 있었는지만 알린다.
+
+/// 마지막으로 읽은 Caps Lock 상태와 트리거 누름 전 상태
+#[derive(Debug, Default)]
+pub struct CapsGuard {
+    /// 마지막으로 읽은 전역 상태 (Caps Lock이 Windows로 간 뒤 다시 읽기 전이면 `None`)
+    known: Option<bool>,
+    /// 지키고 있는 트리거 누름 전의 상태
+    before: Option<bool>,
+}
+
+impl CapsGuard {
+    /// 상태를 모르는 가드 (`static` 초기화용)
+    pub const fn new() -> Self {
+        Self {
+            known: None,
+            before: None,
+        }
+    }
+
+    /// 트리거가 동작을 실행하거나 탭 판정을 시작한 Caps Lock 누름. 이어지는 누름이면
+    /// 처음 누름 전의 상태를 그대로 둔다. 누름 전 상태를 몰라서 지킬 수 없으면 `false`
+    pub fn guard(&mut self) -> bool {
+        if self.before.is_none() {
+            self.before = self.known;
+        }
+        self.before.is_some()
+    }
+
+    /// Caps Lock 다운이 Windows로 갔다 (트리거가 아니거나 홀드로 확정된 누름).
+    /// 원래 기능이므로 지키던 상태를 버리고, 다시 읽을 때까지 상태를 모른다.
+    pub fn passed(&mut self) {
+        self.known = None;
+        self.before = None;
+    }
+
+    /// 메인 스레드에서 읽은 지금 전역 상태를 알린다. 지키던 누름 전 상태와 다르면
+    /// `true`를 돌려주고, 호출하는 쪽이 Caps Lock을 한 번 눌러 되돌린다.
+    pub fn check(&mut self, now: bool) -> bool {
+        let restore = matches!(self.before.take(), Some(before) if before != now);
+        // 되돌리면 누름 전 상태가 된다
+        self.known = Some(now != restore);
+        restore
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn restores_a_state_changed_behind_the_trigger() {
+        let mut caps = CapsGuard::new();
+        assert!(!caps.check(false));
+
+        // Synergy가 먼저 켜 놓은 Caps Lock을 트리거가 가로챘다
+        assert!(caps.guard());
+        assert!(caps.check(true));
+        // 되돌린 뒤에는 꺼진 상태로 알고 있다
+        assert!(caps.guard());
+        assert!(!caps.check(false));
+    }
+
+    #[test]
+    fn unchanged_state_is_left_alone() {
+        let mut caps = CapsGuard::new();
+        caps.check(true);
+        assert!(caps.guard());
+        assert!(!caps.check(true));
+        // 지키던 누름이 없으면 상태만 새로 읽는다
+        assert!(!caps.check(false));
+        assert!(caps.guard());
+        assert!(!caps.check(false));
+    }
+
+    #[test]
+    fn repeated_presses_keep_the_first_state() {
+        let mut caps = CapsGuard::new();
+        caps.check(false);
+        assert!(caps.guard());
+        // 확인 전에 다시 누른 트리거도 처음 누름 전 상태를 기준으로 한다
+        assert!(caps.guard());
+        assert!(caps.check(true));
+        assert!(!caps.check(false));
+    }
+
+    #[test]
+    fn passed_caps_lock_is_not_restored() {
+        let mut caps = CapsGuard::new();
+        caps.check(false);
+        assert!(caps.guard());
+        // 홀드로 확정돼 Caps Lock이 Windows로 갔다
+        caps.passed();
+        assert!(!caps.check(true));
+
+        // 다시 읽기 전에는 누름 전 상태를 몰라서 지키지 않는다
+        caps.passed();
+        assert!(!caps.guard());
+        assert!(!caps.check(false));
+        assert!(caps.guard());
+    }
+
+    #[test]
+    fn unknown_state_is_never_restored() {
+        let mut caps = CapsGuard::new();
+        assert!(!caps.guard());
+        assert!(!caps.check(true));
+    }
+}
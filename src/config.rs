@@ -0,0 +1,1207 @@
+//! 설정 파일 (`config.toml`)
+//!
+//! 트리거 테이블, 트리거 모드, 로깅, UI 옵션을 하나의 타입으로 읽고 쓴다.
+//! 트리거 테이블은 이름 붙은 프로필(`[[profiles]]`)로 여러 벌 둘 수 있고,
+//! 맨 위의 `triggers`는 이름 없는 기본 프로필이다.
+//! 엔진 상태만 바뀌어도 파일 전체를 다시 쓰므로, 엔진이 모르는 설정(로깅, UI)도
+//! 이 구조체에 그대로 남아 있어야 한다.
+//! 이전 버전의 `config.ini`는 [`Config::from_ini`]로 한 번 읽어 옮긴다.
+//! 알 수 없는 키는 여기서는 무시하고, 경고는 [`crate::validate`]가 따로 모은다.
+
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::engine::{Action, Binding, Engine, HoldAction, Source, TapHold, TriggerMode};
+use crate::memory::RememberScope;
+use crate::rules::{InputMode, Method, Rule};
+use crate::strategy::DEFAULT_VERIFY_MS;
+use crate::trigger::Trigger;
+
+/// 설정 파일 이름
+pub const CONFIG_FILE: &str = "config.toml";
+
+/// 맨 위 `triggers`로 이루어진 이름 없는 프로필의 표시 이름
+pub const DEFAULT_PROFILE: &str = "기본";
+
+/// 이전 버전의 설정 파일 이름
+pub const LEGACY_CONFIG_FILE: &str = "config.ini";
+
+/// 실행 파일 옆의 파일 경로
+pub fn exe_dir_file(name: &str) -> PathBuf {
+    if let Ok(exe) = std::env::current_exe() {
+        if let Some(dir) = exe.parent() {
+            return dir.join(name);
+        }
+    }
+    PathBuf::from(name)
+}
+
+// ── 설정 파일 위치 ────────────────────────────────────────────────────────
+
+/// 설정 파일 경로를 지정하는 환경 변수
+pub const CONFIG_ENV: &str = "SYNERGY_HANGUL_FIX_CONFIG";
+
+/// 사용자별 설정 디렉토리 이름 (`%APPDATA%` 아래)
+pub const APP_DIR: &str = "synergy-hangul-fix";
+
+/// 사용 중인 설정 파일을 어떻게 찾았는지
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LocationKind {
+    /// `--config <경로>`
+    CommandLine,
+    /// [`CONFIG_ENV`] 환경 변수
+    Environment,
+    /// 사용자별 앱 데이터 디렉토리
+    User,
+    /// 실행 파일 옆 (포터블 모드)
+    Portable,
+}
+
+impl LocationKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            LocationKind::CommandLine => "명령줄",
+            LocationKind::Environment => "환경 변수",
+            LocationKind::User => "사용자별",
+            LocationKind::Portable => "포터블",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Location {
+    pub path: PathBuf,
+    pub kind: LocationKind,
+}
+
+/// 설정 파일 후보 경로들
+#[derive(Clone, Debug, Default)]
+pub struct Candidates {
+    pub flag: Option<PathBuf>,
+    pub env: Option<PathBuf>,
+    /// 사용자별 설정 디렉토리 (`%APPDATA%\synergy-hangul-fix`)
+    pub user_dir: Option<PathBuf>,
+    pub exe_dir: Option<PathBuf>,
+}
+
+impl Candidates {
+    /// 현재 프로세스의 환경 변수와 실행 파일 위치로 후보를 채운다
+    pub fn from_env(flag: Option<PathBuf>) -> Self {
+        Self {
+            flag,
+            env: std::env::var_os(CONFIG_ENV)
+                .filter(|v| !v.is_empty())
+                .map(PathBuf::from),
+            user_dir: user_config_dir(),
+            exe_dir: std::env::current_exe()
+                .ok()
+                .and_then(|exe| exe.parent().map(PathBuf::from)),
+        }
+    }
+
+    /// 찾는 순서: 명령줄 → 환경 변수 → 사용자별 파일 → 실행 파일 옆 파일.
+    /// 아무 파일도 없으면 사용자별 디렉토리에 새로 만든다 (없으면 실행 파일 옆).
+    pub fn resolve(&self, exists: impl Fn(&Path) -> bool) -> Location {
+        let at = |path: PathBuf, kind| Location { path, kind };
+        if let Some(path) = &self.flag {
+            return at(path.clone(), LocationKind::CommandLine);
+        }
+        if let Some(path) = &self.env {
+            return at(path.clone(), LocationKind::Environment);
+        }
+        let user = self.user_dir.as_ref().map(|d| d.join(CONFIG_FILE));
+        let portable = self.exe_dir.as_ref().map(|d| d.join(CONFIG_FILE));
+        if let Some(path) = user.as_ref().filter(|p| exists(p)) {
+            return at(path.clone(), LocationKind::User);
+        }
+        if let Some(path) = portable.as_ref().filter(|p| exists(p)) {
+            return at(path.clone(), LocationKind::Portable);
+        }
+        match (user, portable) {
+            (Some(path), _) => at(path, LocationKind::User),
+            (None, Some(path)) => at(path, LocationKind::Portable),
+            (None, None) => at(PathBuf::from(CONFIG_FILE), LocationKind::Portable),
+        }
+    }
+}
+
+/// 사용자별 설정 디렉토리 (Windows는 `%APPDATA%`, 그 밖에는 XDG 규칙)
+pub fn user_config_dir() -> Option<PathBuf> {
+    let base = std::env::var_os("APPDATA")
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| {
+            std::env::var_os("XDG_CONFIG_HOME")
+                .filter(|v| !v.is_empty())
+                .map(PathBuf::from)
+        })
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(base.join(APP_DIR))
+}
+
+/// 같은 디렉토리의 임시 파일에 쓴 뒤 이름을 바꿔, 쓰다 만 설정 파일이 남지 않게 한다
+pub fn write_atomic(path: &Path, content: &str) -> io::Result<()> {
+    if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+        fs::create_dir_all(dir)?;
+    }
+    let mut tmp_name = path.file_name().unwrap_or_default().to_os_string();
+    tmp_name.push(".tmp");
+    let tmp = path.with_file_name(tmp_name);
+
+    let result = File::create(&tmp)
+        .and_then(|mut file| {
+            file.write_all(content.as_bytes())?;
+            file.sync_all()
+        })
+        .and_then(|()| fs::rename(&tmp, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    result
+}
+
+// ── 설정 모델 ─────────────────────────────────────────────────────────────
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// 트리거 동작 여부 (트레이 아이콘 클릭으로 켜고 끄는 상태)
+    pub enabled: bool,
+    /// 사용 중인 프로필 이름 (없으면 맨 위 `triggers`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+    /// 트리거 판정 방식 (`press` / `tap`)
+    #[serde(with = "as_str")]
+    pub mode: TriggerMode,
+    /// 트리거를 누르고 있을 때 자동 반복마다 동작 실행
+    pub repeat_toggles: bool,
+    pub tap_hold: TapHoldConfig,
+    pub triggers: Vec<TriggerEntry>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub profiles: Vec<Profile>,
+    /// 앱별 규칙 (모든 프로필에 적용)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub rules: Vec<RuleEntry>,
+    pub ime: ImeConfig,
+    pub caps_lock: CapsLockConfig,
+    pub indicator: IndicatorConfig,
+    pub logging: LoggingConfig,
+    pub ui: UiConfig,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        let engine = Engine::default();
+        Self {
+            enabled: true,
+            profile: None,
+            mode: TriggerMode::Press,
+            repeat_toggles: false,
+            tap_hold: TapHoldConfig::default(),
+            triggers: engine.bindings().iter().map(|&b| b.into()).collect(),
+            profiles: Vec::new(),
+            rules: Vec::new(),
+            ime: ImeConfig::default(),
+            caps_lock: CapsLockConfig::default(),
+            indicator: IndicatorConfig::default(),
+            logging: LoggingConfig::default(),
+            ui: UiConfig::default(),
+        }
+    }
+}
+
+/// `[tap_hold]` 섹션
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TapHoldConfig {
+    pub hold_ms: u32,
+    /// `passthrough` 또는 바꿔 보낼 키 (`LeftCtrl`, `0xA2`)
+    #[serde(with = "as_str")]
+    pub hold_action: HoldAction,
+}
+
+impl Default for TapHoldConfig {
+    fn default() -> Self {
+        TapHold::default().into()
+    }
+}
+
+impl From<TapHold> for TapHoldConfig {
+    fn from(t: TapHold) -> Self {
+        Self {
+            hold_ms: t.hold_ms,
+            hold_action: t.hold,
+        }
+    }
+}
+
+impl From<TapHoldConfig> for TapHold {
+    fn from(t: TapHoldConfig) -> Self {
+        Self {
+            hold_ms: t.hold_ms,
+            hold: t.hold_action,
+        }
+    }
+}
+
+/// `[[triggers]]` 항목 하나
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TriggerEntry {
+    /// `Ctrl+RightAlt`, `LeftAlt@0xE038` 형식
+    #[serde(with = "as_str")]
+    pub key: Trigger,
+    #[serde(with = "as_str", default = "default_action")]
+    pub action: Action,
+    #[serde(with = "as_str", default, skip_serializing_if = "is_any")]
+    pub source: Source,
+}
+
+fn default_action() -> Action {
+    Action::ToggleHangul
+}
+
+fn is_any(source: &Source) -> bool {
+    *source == Source::Any
+}
+
+impl From<Binding> for TriggerEntry {
+    fn from(b: Binding) -> Self {
+        Self {
+            key: b.trigger,
+            action: b.action,
+            source: b.source,
+        }
+    }
+}
+
+impl From<TriggerEntry> for Binding {
+    fn from(e: TriggerEntry) -> Self {
+        Binding::new(e.key, e.action).with_source(e.source)
+    }
+}
+
+/// `[[profiles]]` 항목 하나: 이름 붙은 트리거 테이블
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Profile {
+    pub name: String,
+    #[serde(default)]
+    pub triggers: Vec<TriggerEntry>,
+}
+
+/// `[[rules]]` 항목 하나
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RuleEntry {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub process: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub class: Option<String>,
+    #[serde(with = "opt_as_str", skip_serializing_if = "Option::is_none")]
+    pub key: Option<Trigger>,
+    #[serde(skip_serializing_if = "is_false")]
+    pub disable: bool,
+    #[serde(with = "opt_as_str", skip_serializing_if = "Option::is_none")]
+    pub action: Option<Action>,
+    #[serde(with = "as_str", skip_serializing_if = "is_auto")]
+    pub method: Method,
+    #[serde(with = "opt_as_str", skip_serializing_if = "Option::is_none")]
+    pub mode: Option<InputMode>,
+}
+
+fn is_false(b: &bool) -> bool {
+    !*b
+}
+
+fn is_auto(method: &Method) -> bool {
+    *method == Method::Auto
+}
+
+impl From<Rule> for RuleEntry {
+    fn from(r: Rule) -> Self {
+        Self {
+            process: r.process,
+            class: r.class,
+            key: r.key,
+            disable: r.disable,
+            action: r.action,
+            method: r.method,
+            mode: r.mode,
+        }
+    }
+}
+
+impl From<RuleEntry> for Rule {
+    fn from(e: RuleEntry) -> Self {
+        Self {
+            process: e.process,
+            class: e.class,
+            key: e.key,
+            disable: e.disable,
+            action: e.action,
+            method: e.method,
+            mode: e.mode,
+        }
+    }
+}
+
+/// `[ime]` 섹션
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ImeConfig {
+    /// 트리거로 바꾼 한/영 상태를 창(`window`)이나 프로세스(`process`)마다 기억했다가
+    /// 다시 포그라운드가 되면 되돌린다 (`off`면 사용 안 함)
+    #[serde(with = "as_str")]
+    pub remember: RememberScope,
+    /// 전환한 뒤 이만큼 기다렸다가 변환 모드를 다시 읽어 확인한다 (0이면 확인 안 함)
+    pub verify_ms: u32,
+}
+
+impl Default for ImeConfig {
+    fn default() -> Self {
+        Self {
+            remember: RememberScope::default(),
+            verify_ms: DEFAULT_VERIFY_MS,
+        }
+    }
+}
+
+/// `[caps_lock]` 섹션: Caps Lock을 트리거로 쓸 때
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CapsLockConfig {
+    /// 트리거로 가로챈 Caps Lock이 대문자 고정 상태를 바꿔 놓았으면 되돌린다
+    pub preserve: bool,
+    /// Shift+Caps Lock은 트리거 대신 원래 Caps Lock으로 보낸다
+    pub shift_passthrough: bool,
+}
+
+/// 표시기를 보여 주는 기본 시간 (ms)
+pub const DEFAULT_INDICATOR_MS: u32 = 800;
+
+/// `[indicator]` 섹션: 전환 뒤 캐럿(없으면 마우스 커서) 옆에 잠깐 뜨는 "한"/"A"
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IndicatorConfig {
+    pub enabled: bool,
+    /// 보여 주는 시간
+    pub duration_ms: u32,
+    /// 창이 포커스를 받을 때도 보여 준다
+    pub on_focus: bool,
+}
+
+impl Default for IndicatorConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            duration_ms: DEFAULT_INDICATOR_MS,
+            on_focus: false,
+        }
+    }
+}
+
+/// `[logging]` 섹션
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LoggingConfig {
+    /// 시작할 때 디버그 윈도우를 연다
+    pub debug_window: bool,
+    /// 디버그 로그를 덧붙여 쓸 파일 (없으면 기록하지 않음)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<PathBuf>,
+}
+
+/// `[ui]` 섹션
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UiConfig {
+    /// 키 감지 후 확인 다이얼로그를 띄운다
+    pub confirm_learned_key: bool,
+    /// 트리거 삭제 전에 확인한다
+    pub confirm_remove: bool,
+}
+
+impl Default for UiConfig {
+    fn default() -> Self {
+        Self {
+            confirm_learned_key: true,
+            confirm_remove: true,
+        }
+    }
+}
+
+// ── 엔진 연동 ─────────────────────────────────────────────────────────────
+
+impl Config {
+    /// 사용 중인 프로필 이름. 기본 프로필이거나 없는 이름이면 `None`
+    pub fn active_profile(&self) -> Option<&str> {
+        let name = self.profile.as_deref()?;
+        self.profiles.iter().any(|p| p.name == name).then_some(name)
+    }
+
+    /// 프로필 전환 (`None`이면 기본 프로필). 없는 이름이면 `false`
+    pub fn select_profile(&mut self, name: Option<&str>) -> bool {
+        if name.is_some_and(|n| !self.profiles.iter().any(|p| p.name == n)) {
+            return false;
+        }
+        self.profile = name.map(str::to_string);
+        true
+    }
+
+    /// 사용 중인 프로필의 트리거 테이블
+    pub fn active_triggers(&self) -> &[TriggerEntry] {
+        match self.active_profile() {
+            Some(name) => {
+                let profile = self.profiles.iter().find(|p| p.name == name);
+                profile.map_or(&[], |p| &p.triggers)
+            }
+            None => &self.triggers,
+        }
+    }
+
+    fn active_triggers_mut(&mut self) -> &mut Vec<TriggerEntry> {
+        let name = self.active_profile().map(str::to_string);
+        match self
+            .profiles
+            .iter_mut()
+            .find(|p| Some(&p.name) == name.as_ref())
+        {
+            Some(profile) => &mut profile.triggers,
+            None => &mut self.triggers,
+        }
+    }
+
+    pub fn bindings(&self) -> Vec<Binding> {
+        self.active_triggers().iter().map(|&e| e.into()).collect()
+    }
+
+    pub fn rules(&self) -> Vec<Rule> {
+        self.rules.iter().cloned().map(Rule::from).collect()
+    }
+
+    /// 트리거 관련 설정을 엔진에 반영
+    pub fn apply(&self, engine: &mut Engine) {
+        engine.set_enabled(self.enabled);
+        engine.set_bindings(self.bindings());
+        engine.set_rules(self.rules());
+        engine.set_mode(self.mode);
+        engine.set_tap_hold(self.tap_hold.into());
+        engine.set_repeat_toggles(self.repeat_toggles);
+        engine.set_caps_shift_passthrough(self.caps_lock.shift_passthrough);
+    }
+
+    /// 엔진의 현재 트리거 설정을 가져온다 (로깅·UI 설정은 그대로)
+    pub fn capture(&mut self, engine: &Engine) {
+        self.enabled = engine.enabled();
+        *self.active_triggers_mut() = engine.bindings().iter().map(|&b| b.into()).collect();
+        self.mode = engine.mode();
+        self.tap_hold = engine.tap_hold().into();
+        self.repeat_toggles = engine.repeat_toggles();
+    }
+
+    /// `old`에서 `self`로 바뀐 항목을 사람이 읽을 수 있게 나열한다 (다시 읽기 로그용)
+    pub fn changes_from(&self, old: &Config) -> Vec<String> {
+        // 필드가 늘면 여기서 컴파일이 깨지도록 모든 필드를 적어 둔다
+        let Config {
+            enabled: _,
+            profile: _,
+            mode: _,
+            repeat_toggles: _,
+            tap_hold:
+                TapHoldConfig {
+                    hold_ms: _,
+                    hold_action: _,
+                },
+            triggers: _,
+            profiles: _,
+            rules: _,
+            ime:
+                ImeConfig {
+                    remember: _,
+                    verify_ms: _,
+                },
+            caps_lock:
+                CapsLockConfig {
+                    preserve: _,
+                    shift_passthrough: _,
+                },
+            indicator:
+                IndicatorConfig {
+                    enabled: _,
+                    duration_ms: _,
+                    on_focus: _,
+                },
+            logging:
+                LoggingConfig {
+                    debug_window: _,
+                    file: _,
+                },
+            ui:
+                UiConfig {
+                    confirm_learned_key: _,
+                    confirm_remove: _,
+                },
+        } = self;
+
+        let mut changes = Vec::new();
+        let mut field = |name: &str, before: String, after: String| {
+            if before != after {
+                changes.push(format!("{}: {} → {}", name, before, after));
+            }
+        };
+        field("enabled", old.enabled.to_string(), self.enabled.to_string());
+        field(
+            "profile",
+            old.active_profile().unwrap_or(DEFAULT_PROFILE).to_string(),
+            self.active_profile().unwrap_or(DEFAULT_PROFILE).to_string(),
+        );
+        field("mode", old.mode.to_string(), self.mode.to_string());
+        field(
+            "repeat_toggles",
+            old.repeat_toggles.to_string(),
+            self.repeat_toggles.to_string(),
+        );
+        field(
+            "tap_hold.hold_ms",
+            old.tap_hold.hold_ms.to_string(),
+            self.tap_hold.hold_ms.to_string(),
+        );
+        field(
+            "tap_hold.hold_action",
+            old.tap_hold.hold_action.to_string(),
+            self.tap_hold.hold_action.to_string(),
+        );
+        field(
+            "ime.remember",
+            old.ime.remember.to_string(),
+            self.ime.remember.to_string(),
+        );
+        field(
+            "ime.verify_ms",
+            old.ime.verify_ms.to_string(),
+            self.ime.verify_ms.to_string(),
+        );
+        field(
+            "caps_lock.preserve",
+            old.caps_lock.preserve.to_string(),
+            self.caps_lock.preserve.to_string(),
+        );
+        field(
+            "caps_lock.shift_passthrough",
+            old.caps_lock.shift_passthrough.to_string(),
+            self.caps_lock.shift_passthrough.to_string(),
+        );
+        field(
+            "indicator.enabled",
+            old.indicator.enabled.to_string(),
+            self.indicator.enabled.to_string(),
+        );
+        field(
+            "indicator.duration_ms",
+            old.indicator.duration_ms.to_string(),
+            self.indicator.duration_ms.to_string(),
+        );
+        field(
+            "indicator.on_focus",
+            old.indicator.on_focus.to_string(),
+            self.indicator.on_focus.to_string(),
+        );
+        let path = |p: &Option<PathBuf>| match p {
+            Some(p) => p.display().to_string(),
+            None => "none".to_string(),
+        };
+        field(
+            "logging.debug_window",
+            old.logging.debug_window.to_string(),
+            self.logging.debug_window.to_string(),
+        );
+        field(
+            "logging.file",
+            path(&old.logging.file),
+            path(&self.logging.file),
+        );
+        field(
+            "ui.confirm_learned_key",
+            old.ui.confirm_learned_key.to_string(),
+            self.ui.confirm_learned_key.to_string(),
+        );
+        field(
+            "ui.confirm_remove",
+            old.ui.confirm_remove.to_string(),
+            self.ui.confirm_remove.to_string(),
+        );
+
+        let bindings = |t: &[TriggerEntry]| t.iter().map(|&e| Binding::from(e)).collect::<Vec<_>>();
+        list_changes(
+            &mut changes,
+            "trigger",
+            &bindings(&old.triggers),
+            &bindings(&self.triggers),
+        );
+        let names = |c: &Config| {
+            c.profiles
+                .iter()
+                .map(|p| p.name.clone())
+                .collect::<Vec<_>>()
+        };
+        list_changes(&mut changes, "profile", &names(old), &names(self));
+        for profile in &self.profiles {
+            if let Some(before) = old.profiles.iter().find(|p| p.name == profile.name) {
+                list_changes(
+                    &mut changes,
+                    &format!("profile {} trigger", profile.name),
+                    &bindings(&before.triggers),
+                    &bindings(&profile.triggers),
+                );
+            }
+        }
+        list_changes(&mut changes, "rule", &old.rules(), &self.rules());
+        changes
+    }
+
+    pub fn to_toml(&self) -> String {
+        toml::to_string_pretty(self).expect("config is always serializable")
+    }
+
+    /// 이전 버전의 `key=value` 형식. 알 수 없는 줄과 잘못된 값은 건너뛴다.
+    pub fn from_ini(content: &str) -> Self {
+        let mut config = Self::default();
+        let mut triggers = Vec::new();
+        for line in content.lines() {
+            let Some((key, val)) = line.split_once('=') else {
+                continue;
+            };
+            let val = val.trim();
+            match key.trim() {
+                // 더 이전 버전의 단일 트리거 키 (한/영 전환)
+                "trigger_key" | "trigger" => {
+                    if let Ok(binding) = val.parse::<Binding>() {
+                        triggers.push(binding.into());
+                    }
+                }
+                "trigger_mode" => {
+                    if let Ok(mode) = val.parse() {
+                        config.mode = mode;
+                    }
+                }
+                "hold_ms" => {
+                    if let Ok(ms) = val.parse() {
+                        config.tap_hold.hold_ms = ms;
+                    }
+                }
+                "hold_action" => {
+                    if let Ok(hold) = val.parse() {
+                        config.tap_hold.hold_action = hold;
+                    }
+                }
+                "repeat_toggles" => {
+                    config.repeat_toggles = val.eq_ignore_ascii_case("true") || val == "1";
+                }
+                _ => {}
+            }
+        }
+        if !triggers.is_empty() {
+            config.triggers = triggers;
+        }
+        config
+    }
+}
+
+impl FromStr for Config {
+    type Err = toml::de::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        toml::from_str(s)
+    }
+}
+
+/// `old`에서 `new`로 빠진 항목과 더해진 항목을 적는다.
+/// 항목은 그대로이고 순서만 바뀌었으면 그렇다고 적는다 (트리거·규칙은 앞의 것이 먼저 맞는다).
+fn list_changes<T: PartialEq + fmt::Display>(
+    changes: &mut Vec<String>,
+    label: &str,
+    old: &[T],
+    new: &[T],
+) {
+    let len = changes.len();
+    for item in old.iter().filter(|item| !new.contains(item)) {
+        changes.push(format!("{} removed: {}", label, item));
+    }
+    for item in new.iter().filter(|item| !old.contains(item)) {
+        changes.push(format!("{} added: {}", label, item));
+    }
+    if changes.len() == len && old != new {
+        changes.push(format!("{} order changed", label));
+    }
+}
+
+// ── 문자열 필드 ───────────────────────────────────────────────────────────
+
+/// `Display`/`FromStr`가 있는 타입을 TOML 문자열로 읽고 쓴다
+mod as_str {
+    use super::*;
+    use serde::{de, Deserializer, Serializer};
+
+    pub fn serialize<T: fmt::Display, S: Serializer>(
+        value: &T,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(value)
+    }
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(de::Error::custom)
+    }
+}
+
+/// [`as_str`]의 `Option` 버전 (값이 없으면 키를 쓰지 않는다)
+mod opt_as_str {
+    use super::*;
+    use serde::{Deserializer, Serializer};
+
+    pub fn serialize<T: fmt::Display, S: Serializer>(
+        value: &Option<T>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match value {
+            Some(value) => serializer.collect_str(value),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+        D: Deserializer<'de>,
+    {
+        as_str::deserialize(deserializer).map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trigger::{Modifiers, ScanCode};
+
+    #[test]
+    fn empty_file_is_default() {
+        assert_eq!("".parse::<Config>().unwrap(), Config::default());
+    }
+
+    #[test]
+    fn parse_full_file() {
+        let config: Config = r#"
+            mode = "tap"
+            repeat_toggles = true
+
+            [tap_hold]
+            hold_ms = 250
+            hold_action = "0xA2"
+
+            [[triggers]]
+            key = "Ctrl+0xA5"
+            action = "set_hangul"
+
+            [[triggers]]
+            key = "0xA4@0xE038"
+            source = "injected"
+
+            [logging]
+            debug_window = true
+            file = 'C:\temp\hangul.log'
+
+            [ui]
+            confirm_remove = false
+        "#
+        .parse()
+        .unwrap();
+
+        assert_eq!(config.mode, TriggerMode::Tap);
+        assert!(config.repeat_toggles);
+        assert_eq!(
+            TapHold::from(config.tap_hold),
+            TapHold {
+                hold_ms: 250,
+                hold: HoldAction::Modifier(0xA2)
+            }
+        );
+        assert_eq!(
+            config.bindings(),
+            vec![
+                Binding::new(Trigger::chord(Modifiers::CTRL, 0xA5), Action::SetHangul),
+                Binding::new(
+                    Trigger::single(0xA4).with_scan(ScanCode::new(0x38, true)),
+                    Action::ToggleHangul
+                )
+                .with_source(Source::Injected),
+            ]
+        );
+        assert!(config.logging.debug_window);
+        assert_eq!(
+            config.logging.file,
+            Some(PathBuf::from(r"C:\temp\hangul.log"))
+        );
+        assert!(config.ui.confirm_learned_key);
+        assert!(!config.ui.confirm_remove);
+    }
+
+    #[test]
+    fn empty_trigger_list_stays_empty() {
+        let config: Config = "triggers = []".parse().unwrap();
+        assert!(config.triggers.is_empty());
+    }
+
+    #[test]
+    fn invalid_values_are_errors() {
+        assert!("mode = \"hold\"".parse::<Config>().is_err());
+        assert!("[[triggers]]\nkey = \"Hyper+0x20\""
+            .parse::<Config>()
+            .is_err());
+        assert!("[[triggers]]\nkey = \"0x14\"\naction = \"launch\""
+            .parse::<Config>()
+            .is_err());
+    }
+
+    #[test]
+    fn serialize_round_trips() {
+        let mut config = Config {
+            mode: TriggerMode::Tap,
+            ..Config::default()
+        };
+        config.triggers.push(
+            Binding::new(
+                Trigger::chord(Modifiers::LSHIFT, 0x20),
+                Action::ConvertHanja,
+            )
+            .with_source(Source::Local)
+            .into(),
+        );
+        config.logging.file = Some(PathBuf::from("log.txt"));
+        config.ui.confirm_learned_key = false;
+
+        let text = config.to_toml();
+        assert_eq!(text.parse::<Config>().unwrap(), config);
+        assert!(text.contains("key = \"LShift+Space\""));
+        assert!(text.contains("source = \"local\""));
+    }
+
+    #[test]
+    fn apply_and_capture_engine_state() {
+        let mut config = Config {
+            repeat_toggles: true,
+            ..Config::default()
+        };
+        config.logging.debug_window = true;
+        config.caps_lock.shift_passthrough = true;
+
+        let mut engine = Engine::new();
+        config.apply(&mut engine);
+        assert!(engine.repeat_toggles());
+        assert!(engine.caps_shift_passthrough());
+        assert_eq!(engine.bindings(), Engine::default().bindings());
+
+        engine.set_mode(TriggerMode::Tap);
+        engine.bind(Binding::new(Trigger::single(0x7C), Action::SetEnglish));
+        config.capture(&engine);
+        assert_eq!(config.mode, TriggerMode::Tap);
+        assert_eq!(config.triggers.len(), 2);
+        // 엔진이 모르는 설정은 유지
+        assert!(config.logging.debug_window);
+    }
+
+    #[test]
+    fn profiles_switch_trigger_table() {
+        let mut config: Config = r#"
+            profile = "direct"
+
+            [[triggers]]
+            key = "LeftAlt"
+
+            [[profiles]]
+            name = "direct"
+
+            [[profiles.triggers]]
+            key = "RightAlt"
+            action = "set_hangul"
+        "#
+        .parse()
+        .unwrap();
+        assert_eq!(config.active_profile(), Some("direct"));
+        assert_eq!(
+            config.bindings(),
+            vec![Binding::new(Trigger::single(0xA5), Action::SetHangul)]
+        );
+
+        // 엔진에서 바꾼 트리거는 사용 중인 프로필에만 들어간다
+        let mut engine = Engine::new();
+        config.apply(&mut engine);
+        engine.bind(Binding::new(Trigger::single(0x7C), Action::SetEnglish));
+        config.capture(&engine);
+        assert_eq!(config.profiles[0].triggers.len(), 2);
+        assert_eq!(config.triggers.len(), 1);
+
+        assert!(config.select_profile(None));
+        assert_eq!(config.active_profile(), None);
+        assert_eq!(
+            config.bindings(),
+            vec![Binding::new(Trigger::single(0xA4), Action::ToggleHangul)]
+        );
+        assert!(!config.select_profile(Some("missing")));
+        assert_eq!(config.profile, None);
+
+        assert!(config.select_profile(Some("direct")));
+        let text = config.to_toml();
+        assert_eq!(text.parse::<Config>().unwrap(), config);
+        assert!(text.contains("profile = \"direct\""));
+    }
+
+    #[test]
+    fn rules_parse_and_reach_engine() {
+        let config: Config = r#"
+            [[rules]]
+            process = "WindowsTerminal.exe"
+            key = "CapsLock"
+            disable = true
+
+            [[rules]]
+            class = "TscShellContainerClass"
+            action = "set_english"
+            method = "send_input"
+        "#
+        .parse()
+        .unwrap();
+        assert_eq!(
+            config.rules(),
+            vec![
+                Rule {
+                    process: Some("WindowsTerminal.exe".to_string()),
+                    key: Some(Trigger::single(0x14)),
+                    disable: true,
+                    ..Rule::default()
+                },
+                Rule {
+                    class: Some("TscShellContainerClass".to_string()),
+                    action: Some(Action::SetEnglish),
+                    method: Method::SendInput,
+                    ..Rule::default()
+                },
+            ]
+        );
+
+        let mut engine = Engine::new();
+        config.apply(&mut engine);
+        assert_eq!(engine.rules().len(), 2);
+        assert_eq!(config.to_toml().parse::<Config>().unwrap(), config);
+        assert_eq!(
+            Config::default().changes_from(&config),
+            vec![
+                "rule removed: process=WindowsTerminal.exe key=CapsLock → disable",
+                "rule removed: class=TscShellContainerClass → action=set_english method=send_input",
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_profile_falls_back_to_top_level() {
+        let config: Config = "profile = \"gone\"\n[[triggers]]\nkey = \"F13\"\n"
+            .parse()
+            .unwrap();
+        assert_eq!(config.active_profile(), None);
+        assert_eq!(config.bindings().len(), 1);
+    }
+
+    #[test]
+    fn changes_list_fields_and_triggers() {
+        let old = Config::default();
+        assert!(old.changes_from(&old).is_empty());
+
+        let mut new = Config {
+            enabled: false,
+            mode: TriggerMode::Tap,
+            ..Config::default()
+        };
+        new.triggers = vec![Binding::new(Trigger::single(0xA5), Action::SetHangul).into()];
+        new.logging.file = Some(PathBuf::from("log.txt"));
+        assert_eq!(
+            new.changes_from(&old),
+            vec![
+                "enabled: true → false",
+                "mode: press → tap",
+                "logging.file: none → log.txt",
+                "trigger removed: CapsLock, toggle_hangul",
+                "trigger added: RightAlt, set_hangul",
+            ]
+        );
+    }
+
+    #[test]
+    fn changes_list_profile_edits() {
+        let old = Config {
+            profiles: vec![Profile {
+                name: "synergy".into(),
+                triggers: vec![Binding::new(Trigger::single(0xA5), Action::ToggleHangul).into()],
+            }],
+            ..Config::default()
+        };
+
+        // 사용 중이 아닌 프로필만 고쳐도 나와야 한다
+        let mut new = old.clone();
+        new.profiles[0].triggers =
+            vec![Binding::new(Trigger::single(0xA5), Action::SetHangul).into()];
+        new.profiles.push(Profile {
+            name: "local".into(),
+            triggers: Vec::new(),
+        });
+        assert_eq!(
+            new.changes_from(&old),
+            vec![
+                "profile added: local",
+                "profile synergy trigger removed: RightAlt, toggle_hangul",
+                "profile synergy trigger added: RightAlt, set_hangul",
+            ]
+        );
+        assert_eq!(old.changes_from(&new)[0], "profile removed: local");
+    }
+
+    #[test]
+    fn changes_list_rule_edits() {
+        let game = RuleEntry {
+            process: Some("game.exe".into()),
+            disable: true,
+            ..RuleEntry::default()
+        };
+        let term = RuleEntry {
+            class: Some("ConsoleWindowClass".into()),
+            method: Method::SendInput,
+            ..RuleEntry::default()
+        };
+        let old = Config {
+            rules: vec![game.clone()],
+            ..Config::default()
+        };
+
+        let mut new = old.clone();
+        new.rules.push(term.clone());
+        let changes = new.changes_from(&old);
+        assert_eq!(changes.len(), 1);
+        assert!(changes[0].starts_with("rule added: "), "{:?}", changes);
+
+        new.rules = vec![term, game];
+        let old = Config {
+            rules: new.rules.iter().rev().cloned().collect(),
+            ..Config::default()
+        };
+        assert_eq!(new.changes_from(&old), vec!["rule order changed"]);
+    }
+
+    fn candidates() -> Candidates {
+        Candidates {
+            flag: None,
+            env: None,
+            user_dir: Some(PathBuf::from("/appdata/synergy-hangul-fix")),
+            exe_dir: Some(PathBuf::from("/opt/app")),
+        }
+    }
+
+    #[test]
+    fn location_lookup_order() {
+        let none = |_: &Path| false;
+        let all = |_: &Path| true;
+
+        let mut c = candidates();
+        c.flag = Some(PathBuf::from("cli.toml"));
+        c.env = Some(PathBuf::from("env.toml"));
+        assert_eq!(c.resolve(all).kind, LocationKind::CommandLine);
+        c.flag = None;
+        assert_eq!(
+            c.resolve(all),
+            Location {
+                path: PathBuf::from("env.toml"),
+                kind: LocationKind::Environment
+            }
+        );
+        c.env = None;
+        assert_eq!(c.resolve(all).kind, LocationKind::User);
+
+        // 사용자별 파일이 없고 실행 파일 옆에 있으면 포터블
+        let portable = |p: &Path| p.starts_with("/opt/app");
+        assert_eq!(
+            c.resolve(portable),
+            Location {
+                path: PathBuf::from("/opt/app").join(CONFIG_FILE),
+                kind: LocationKind::Portable
+            }
+        );
+
+        // 둘 다 없으면 사용자별 디렉토리에 새로 만든다
+        assert_eq!(
+            c.resolve(none).path,
+            PathBuf::from("/appdata/synergy-hangul-fix").join(CONFIG_FILE)
+        );
+        c.user_dir = None;
+        assert_eq!(c.resolve(none).kind, LocationKind::Portable);
+    }
+
+    #[test]
+    fn atomic_write_replaces_file() {
+        let dir = std::env::temp_dir().join(format!("shf-config-test-{}", std::process::id()));
+        let path = dir.join("nested").join(CONFIG_FILE);
+
+        write_atomic(&path, "mode = \"tap\"\n").unwrap();
+        write_atomic(&path, "mode = \"press\"\n").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "mode = \"press\"\n");
+        let leftovers: Vec<_> = fs::read_dir(path.parent().unwrap())
+            .unwrap()
+            .map(|e| e.unwrap().file_name())
+            .collect();
+        assert_eq!(leftovers, vec![std::ffi::OsString::from(CONFIG_FILE)]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn migrate_legacy_ini() {
+        let config = Config::from_ini(
+            "trigger=0xA4, toggle_hangul\n\
+             trigger=Shift+0x20, set_english, injected\n\
+             trigger_mode=tap\n\
+             hold_ms=300\n\
+             hold_action=passthrough\n\
+             repeat_toggles=true\n\
+             garbage line\n",
+        );
+        assert_eq!(
+            config.bindings(),
+            vec![
+                Binding::new(Trigger::single(0xA4), Action::ToggleHangul),
+                Binding::new(Trigger::chord(Modifiers::SHIFT, 0x20), Action::SetEnglish)
+                    .with_source(Source::Injected),
+            ]
+        );
+        assert_eq!(config.mode, TriggerMode::Tap);
+        assert_eq!(config.tap_hold.hold_ms, 300);
+        assert!(config.repeat_toggles);
+    }
+
+    #[test]
+    fn migrate_oldest_single_key_ini() {
+        let config = Config::from_ini("trigger_key=0xA4\n");
+        assert_eq!(
+            config.bindings(),
+            vec![Binding::new(Trigger::single(0xA4), Action::ToggleHangul)]
+        );
+        // 트리거가 없으면 기본값
+        assert_eq!(Config::from_ini(""), Config::default());
+    }
+}
@@ -0,0 +1,1902 @@
+//! 트리거 엔진
+//!
+//! 키보드 훅이 받은 이벤트를 플랫폼 중립적인 [`KeyEvent`]로 넘기면
+//! 통과/차단/동작 실행 여부를 [`Decision`]으로 돌려준다.
+//! 실제 IME 조작이나 메시지 전송은 호출하는 쪽(Win32 어댑터)이 담당한다.
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::keys::{parse_key, KeyName};
+use crate::rules::{self, Rule, RuleMatch, Window};
+use crate::trigger::{Modifiers, ScanCode, Trigger};
+
+/// Caps Lock 가상 키 코드 (기본 트리거)
+pub const VK_CAPITAL: u32 = 0x14;
+
+/// 탭/홀드 판정 기본 임계값 (ms)
+pub const DEFAULT_HOLD_MS: u32 = 200;
+
+/// 같은 키의 다운이 이 간격(ms) 안에 다시 오면 자동 반복으로 본다.
+/// Windows 반복 지연 설정의 최댓값(1초)보다 길게 잡아, 키 업을 놓쳤을 때도
+/// 다음 누름이 반복으로 오인되지 않게 한다.
+pub const REPEAT_GAP_MS: u32 = 1500;
+
+/// 주입된 입력이 이 시간(ms) 동안 없다가 다시 오면 Synergy 화면 재진입으로 본다
+pub const REENTRY_GAP_MS: u32 = 3000;
+
+/// IME 변환 모드의 한글(native) 비트 (`IME_CMODE_NATIVE`)
+pub const IME_CMODE_NATIVE: u32 = 0x0001;
+
+/// `KBDLLHOOKSTRUCT::flags`의 확장 키 비트
+pub const LLKHF_EXTENDED: u32 = 0x01;
+
+/// `KBDLLHOOKSTRUCT::flags`: 더 낮은 무결성 수준의 프로세스가 주입한 입력
+pub const LLKHF_LOWER_IL_INJECTED: u32 = 0x02;
+
+/// `KBDLLHOOKSTRUCT::flags`: `SendInput` 등으로 주입된 입력 (Synergy 포함)
+pub const LLKHF_INJECTED: u32 = 0x10;
+
+/// 이 프로그램이 주입하는 입력의 `dwExtraInfo` 표식 ("SHFX")
+pub const INJECT_SIGNATURE: usize = 0x5348_4658;
+
+// ── 입력 ──────────────────────────────────────────────────────────────────
+
+/// 키 이벤트 방향
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyKind {
+    Down,
+    Up,
+}
+
+/// 키 이벤트의 출처
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Origin {
+    /// 로컬 키보드
+    #[default]
+    Local,
+    /// 다른 프로세스가 주입한 입력 (Synergy 등)
+    Injected,
+    /// 이 프로그램이 주입한 입력 (`INJECT_SIGNATURE`)
+    Own,
+}
+
+impl Origin {
+    /// `KBDLLHOOKSTRUCT`의 `flags`와 `dwExtraInfo`로 출처를 판별한다
+    pub fn classify(flags: u32, extra_info: usize) -> Self {
+        if flags & (LLKHF_INJECTED | LLKHF_LOWER_IL_INJECTED) == 0 {
+            Self::Local
+        } else if extra_info == INJECT_SIGNATURE {
+            Self::Own
+        } else {
+            Self::Injected
+        }
+    }
+}
+
+/// 훅에서 받은 키 이벤트 (`KBDLLHOOKSTRUCT`의 플랫폼 중립 버전)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeyEvent {
+    pub kind: KeyKind,
+    pub vk: u32,
+    pub scan: u32,
+    pub flags: u32,
+    pub origin: Origin,
+    /// 이벤트 타임스탬프 (ms)
+    pub time: u32,
+}
+
+impl KeyEvent {
+    pub fn down(vk: u32) -> Self {
+        Self {
+            kind: KeyKind::Down,
+            vk,
+            scan: 0,
+            flags: 0,
+            origin: Origin::Local,
+            time: 0,
+        }
+    }
+
+    pub fn up(vk: u32) -> Self {
+        Self {
+            kind: KeyKind::Up,
+            ..Self::down(vk)
+        }
+    }
+
+    pub fn at(self, time: u32) -> Self {
+        Self { time, ..self }
+    }
+
+    pub fn with_origin(self, origin: Origin) -> Self {
+        Self { origin, ..self }
+    }
+
+    pub fn is_down(&self) -> bool {
+        self.kind == KeyKind::Down
+    }
+
+    pub fn is_extended(&self) -> bool {
+        self.flags & LLKHF_EXTENDED != 0
+    }
+
+    /// 스캔 코드와 확장 플래그를 지정한 이벤트
+    pub fn with_scan(self, scan: u32, extended: bool) -> Self {
+        let flags = if extended {
+            self.flags | LLKHF_EXTENDED
+        } else {
+            self.flags & !LLKHF_EXTENDED
+        };
+        Self {
+            scan,
+            flags,
+            ..self
+        }
+    }
+
+    pub fn scan_code(&self) -> ScanCode {
+        ScanCode::new(self.scan, self.is_extended())
+    }
+
+    /// 학습 시 캡처할 트리거. 스캔 코드가 0인 합성 이벤트는 가상 키 코드만 쓴다.
+    fn learned_trigger(&self, mods: Modifiers) -> Trigger {
+        let trigger = Trigger::chord(mods, self.vk);
+        if self.scan == 0 {
+            trigger
+        } else {
+            trigger.with_scan(self.scan_code())
+        }
+    }
+}
+
+// ── 트리거 모드 ───────────────────────────────────────────────────────────
+
+/// 트리거 키를 길게 눌렀을 때의 동작
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HoldAction {
+    /// 원래 키로 전달 (Caps Lock, AltGr 등 본래 기능 유지)
+    PassThrough,
+    /// 지정한 가상 키(예: Ctrl)로 바꿔서 전달
+    Modifier(u32),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TriggerMode {
+    /// 키 다운 즉시 동작 실행
+    Press,
+    /// 짧게 탭하면 키 업에서 동작 실행, 길게 누르면 [`TapHold`] 설정대로 처리
+    Tap,
+}
+
+impl fmt::Display for HoldAction {
+    /// 설정 파일 형식: `passthrough` 또는 키 이름 (`LeftCtrl`)
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HoldAction::PassThrough => f.write_str("passthrough"),
+            HoldAction::Modifier(vk) => write!(f, "{}", KeyName(*vk)),
+        }
+    }
+}
+
+impl FromStr for HoldAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.eq_ignore_ascii_case("passthrough") {
+            Ok(HoldAction::PassThrough)
+        } else {
+            parse_key(s)
+                .map(HoldAction::Modifier)
+                .ok_or_else(|| format!("unknown hold action '{}'", s))
+        }
+    }
+}
+
+impl fmt::Display for TriggerMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            TriggerMode::Press => "press",
+            TriggerMode::Tap => "tap",
+        })
+    }
+}
+
+impl FromStr for TriggerMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "press" => Ok(TriggerMode::Press),
+            "tap" => Ok(TriggerMode::Tap),
+            other => Err(format!("unknown trigger mode '{}'", other)),
+        }
+    }
+}
+
+/// 탭/홀드 모드 설정
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TapHold {
+    /// 이 시간(ms) 이상 누르면 홀드. 다른 키와 함께 누르면 시간과 무관하게 홀드
+    pub hold_ms: u32,
+    pub hold: HoldAction,
+}
+
+impl Default for TapHold {
+    fn default() -> Self {
+        Self {
+            hold_ms: DEFAULT_HOLD_MS,
+            hold: HoldAction::PassThrough,
+        }
+    }
+}
+
+// ── 출력 ──────────────────────────────────────────────────────────────────
+
+/// 트리거가 실행할 동작
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    /// 한/영 전환
+    ToggleHangul,
+    /// 현재 상태와 상관없이 한글로
+    SetHangul,
+    /// 현재 상태와 상관없이 영문으로
+    SetEnglish,
+    /// 한자 변환 (한자 키)
+    ConvertHanja,
+}
+
+impl Action {
+    pub const ALL: [Action; 4] = [
+        Action::ToggleHangul,
+        Action::SetHangul,
+        Action::SetEnglish,
+        Action::ConvertHanja,
+    ];
+
+    /// 설정 파일에 쓰는 이름
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::ToggleHangul => "toggle_hangul",
+            Action::SetHangul => "set_hangul",
+            Action::SetEnglish => "set_english",
+            Action::ConvertHanja => "convert_hanja",
+        }
+    }
+
+    /// 트레이 메뉴 등에 표시할 이름
+    pub fn label(self) -> &'static str {
+        match self {
+            Action::ToggleHangul => "한/영 전환",
+            Action::SetHangul => "한글로",
+            Action::SetEnglish => "영문으로",
+            Action::ConvertHanja => "한자 변환",
+        }
+    }
+
+    /// 현재 IME 변환 모드에 이 동작을 적용한 새 변환 모드.
+    /// 해당 비트만 바꾸고 나머지 비트는 그대로 둔다. 한자 변환은 조합 중인 글자에 대한
+    /// 동작이라 변환 모드로는 할 수 없으므로 `None` (한자 키를 주입해야 한다).
+    pub fn apply_conversion(self, conversion: u32) -> Option<u32> {
+        match self {
+            Action::ToggleHangul => Some(conversion ^ IME_CMODE_NATIVE),
+            Action::SetHangul => Some(conversion | IME_CMODE_NATIVE),
+            Action::SetEnglish => Some(conversion & !IME_CMODE_NATIVE),
+            Action::ConvertHanja => None,
+        }
+    }
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Action {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Action::ALL
+            .into_iter()
+            .find(|a| a.name().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| format!("unknown action '{}'", s.trim()))
+    }
+}
+
+/// 트리거 테이블의 한 항목
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Binding {
+    pub trigger: Trigger,
+    pub action: Action,
+    pub source: Source,
+}
+
+impl Binding {
+    pub const fn new(trigger: Trigger, action: Action) -> Self {
+        Self {
+            trigger,
+            action,
+            source: Source::Any,
+        }
+    }
+
+    /// 특정 출처의 이벤트에만 반응하는 트리거
+    pub const fn with_source(self, source: Source) -> Self {
+        Self { source, ..self }
+    }
+
+    /// 같은 트리거 자리인지 (동작은 비교하지 않음)
+    fn same_slot(&self, other: &Self) -> bool {
+        self.trigger == other.trigger && self.source == other.source
+    }
+}
+
+impl fmt::Display for Binding {
+    /// 설정 파일 형식: `Ctrl+0xA5, toggle_hangul` (출처가 있으면 `, injected`)
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}, {}", self.trigger, self.action)?;
+        if self.source != Source::Any {
+            write!(f, ", {}", self.source)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Binding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split(',').map(str::trim);
+        let trigger = parts
+            .next()
+            .unwrap_or_default()
+            .parse()
+            .map_err(|e| format!("{}", e))?;
+        let action = match parts.next() {
+            Some(a) => a.parse()?,
+            None => Action::ToggleHangul,
+        };
+        let source = match parts.next() {
+            Some(src) => src.parse()?,
+            None => Source::Any,
+        };
+        if let Some(extra) = parts.next() {
+            return Err(format!("unexpected '{}'", extra));
+        }
+        Ok(Self {
+            trigger,
+            action,
+            source,
+        })
+    }
+}
+
+/// 트리거가 반응할 이벤트 출처
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Source {
+    #[default]
+    Any,
+    /// 주입된 입력만 (Synergy가 중계한 키)
+    Injected,
+    /// 로컬 키보드만
+    Local,
+}
+
+impl Source {
+    pub const ALL: [Source; 3] = [Source::Any, Source::Injected, Source::Local];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Source::Any => "any",
+            Source::Injected => "injected",
+            Source::Local => "local",
+        }
+    }
+
+    /// 메뉴 표시용 이름
+    pub fn label(self) -> &'static str {
+        match self {
+            Source::Any => "모든 입력",
+            Source::Injected => "Synergy (주입된 입력)만",
+            Source::Local => "로컬 키보드만",
+        }
+    }
+
+    pub fn accepts(self, origin: Origin) -> bool {
+        match self {
+            Source::Any => origin != Origin::Own,
+            Source::Injected => origin == Origin::Injected,
+            Source::Local => origin == Origin::Local,
+        }
+    }
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Source {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|src| src.name().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| format!("unknown source '{}'", s.trim()))
+    }
+}
+
+/// 주입할 키 입력 한 개
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Stroke {
+    pub kind: KeyKind,
+    pub vk: u32,
+    pub scan: u32,
+    pub extended: bool,
+}
+
+impl Stroke {
+    /// 가상 키 코드만으로 만든 입력 (스캔 코드는 시스템이 채운다)
+    pub fn key(vk: u32, kind: KeyKind) -> Self {
+        Self {
+            kind,
+            vk,
+            scan: 0,
+            extended: is_extended_vk(vk),
+        }
+    }
+
+    /// 원래 이벤트를 그대로 재현하는 입력
+    pub fn replay(ev: &KeyEvent, kind: KeyKind) -> Self {
+        Self {
+            kind,
+            vk: ev.vk,
+            scan: ev.scan,
+            extended: ev.is_extended(),
+        }
+    }
+}
+
+/// 오른쪽 수식 키 등 확장 키 플래그가 필요한 가상 키
+fn is_extended_vk(vk: u32) -> bool {
+    matches!(vk, 0xA3 | 0xA5 | 0x5B | 0x5C)
+}
+
+/// 엔진의 판단 결과
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Decision {
+    /// 다음 훅으로 그대로 전달
+    Pass,
+    /// 이벤트 차단
+    Swallow,
+    /// 이벤트를 차단하고 동작 실행
+    Emit(Action),
+    /// 키 학습 모드에서 캡처됨 (이벤트는 차단)
+    Learned(Trigger),
+    /// 이벤트를 차단하고 대신 이 입력들을 순서대로 주입
+    Replay(Vec<Stroke>),
+}
+
+impl Decision {
+    /// 이벤트를 차단해야 하는지
+    pub fn swallows(&self) -> bool {
+        !matches!(self, Decision::Pass)
+    }
+}
+
+// ── 엔진 ──────────────────────────────────────────────────────────────────
+
+/// 탭/홀드 판정 대기 중인 트리거 누름
+#[derive(Clone, Copy, Debug)]
+struct Pending {
+    down: KeyEvent,
+    /// 탭으로 끝나면 실행할 동작
+    action: Action,
+    /// 누른 순간의 탭/홀드 설정 (누르는 도중 설정이 바뀌어도 주입한 다운과 업의 짝을 맞춘다)
+    tap_hold: TapHold,
+    /// 홀드로 확정되어 키 다운을 이미 주입했는지
+    held: bool,
+}
+
+impl Pending {
+    /// 홀드로 확정됐을 때 주입하는 입력
+    fn hold_stroke(&self, kind: KeyKind) -> Stroke {
+        match self.tap_hold.hold {
+            HoldAction::PassThrough => Stroke::replay(&self.down, kind),
+            HoldAction::Modifier(vk) => Stroke::key(vk, kind),
+        }
+    }
+}
+
+/// 디버그 로그용 누적 카운터
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// 차단한 트리거 자동 반복 다운 수
+    pub suppressed_repeats: u64,
+}
+
+#[derive(Debug)]
+pub struct Engine {
+    enabled: bool,
+    bindings: Vec<Binding>,
+    mode: TriggerMode,
+    tap_hold: TapHold,
+    /// 누름 모드에서 자동 반복 다운마다 동작을 다시 실행할지
+    repeat_toggles: bool,
+    /// Shift를 누른 채 Caps Lock을 누르면 트리거 대신 원래 Caps Lock으로 통과
+    caps_shift_passthrough: bool,
+    /// 키별 마지막 다운 타임스탬프 (눌려 있지 않으면 `None`)
+    last_down: [Option<u32>; 256],
+    /// 다운을 차단한 키 (키 업도 짝을 맞춰 차단한다)
+    swallowed: [bool; 256],
+    stats: Stats,
+    pending: Option<Pending>,
+    learning: bool,
+    /// 학습 중 단독으로 눌린 수식 키 (떼는 순간 트리거로 확정)
+    learn_candidate: Option<Trigger>,
+    /// 마지막으로 받은 주입된 입력의 타임스탬프 (화면 재진입 감지용)
+    last_injected: Option<u32>,
+    /// 마지막 주입된 입력 뒤로 로컬 키보드 입력이 있었는지
+    local_since_injected: bool,
+    /// 앱별 규칙 (위에 있는 것이 우선)
+    rules: Vec<Rule>,
+    /// 마지막 트리거 다운에 적용된 규칙
+    rule_match: Option<RuleMatch>,
+}
+
+impl Default for Engine {
+    /// 기본 트리거(Caps Lock → 한/영 전환)가 들어 있는 엔진
+    fn default() -> Self {
+        let mut engine = Self::new();
+        engine.set_bindings(vec![Binding::new(
+            Trigger::single(VK_CAPITAL),
+            Action::ToggleHangul,
+        )]);
+        engine
+    }
+}
+
+impl Engine {
+    /// 트리거 테이블이 빈 엔진 (`static` 초기화용)
+    pub const fn new() -> Self {
+        Self {
+            enabled: true,
+            bindings: Vec::new(),
+            mode: TriggerMode::Press,
+            tap_hold: TapHold {
+                hold_ms: DEFAULT_HOLD_MS,
+                hold: HoldAction::PassThrough,
+            },
+            repeat_toggles: false,
+            caps_shift_passthrough: false,
+            last_down: [None; 256],
+            swallowed: [false; 256],
+            stats: Stats {
+                suppressed_repeats: 0,
+            },
+            pending: None,
+            learning: false,
+            learn_candidate: None,
+            last_injected: None,
+            local_since_injected: false,
+            rules: Vec::new(),
+            rule_match: None,
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    /// 활성화 상태를 뒤집고 새 상태를 돌려준다
+    pub fn toggle_enabled(&mut self) -> bool {
+        self.enabled = !self.enabled;
+        self.enabled
+    }
+
+    pub fn bindings(&self) -> &[Binding] {
+        &self.bindings
+    }
+
+    pub fn set_bindings(&mut self, bindings: Vec<Binding>) {
+        self.bindings = bindings;
+        self.settle_pending();
+    }
+
+    /// 같은 트리거가 있으면 동작을 바꾸고, 없으면 추가한다
+    pub fn bind(&mut self, binding: Binding) {
+        match self.bindings.iter_mut().find(|b| b.same_slot(&binding)) {
+            Some(existing) => existing.action = binding.action,
+            None => self.bindings.push(binding),
+        }
+        self.settle_pending();
+    }
+
+    /// 같은 트리거·출처의 항목만 테이블에서 빼고, 있었으면 `true`
+    pub fn remove_binding(&mut self, binding: Binding) -> bool {
+        let before = self.bindings.len();
+        self.bindings.retain(|b| !b.same_slot(&binding));
+        self.settle_pending();
+        self.bindings.len() != before
+    }
+
+    /// 트리거를 (출처와 관계없이) 테이블에서 빼고, 있었으면 `true`
+    pub fn unbind(&mut self, trigger: Trigger) -> bool {
+        let before = self.bindings.len();
+        self.bindings.retain(|b| b.trigger != trigger);
+        self.settle_pending();
+        self.bindings.len() != before
+    }
+
+    pub fn mode(&self) -> TriggerMode {
+        self.mode
+    }
+
+    pub fn set_mode(&mut self, mode: TriggerMode) {
+        self.mode = mode;
+        self.settle_pending();
+    }
+
+    pub fn tap_hold(&self) -> TapHold {
+        self.tap_hold
+    }
+
+    pub fn set_tap_hold(&mut self, tap_hold: TapHold) {
+        self.tap_hold = tap_hold;
+    }
+
+    pub fn repeat_toggles(&self) -> bool {
+        self.repeat_toggles
+    }
+
+    pub fn set_repeat_toggles(&mut self, repeat_toggles: bool) {
+        self.repeat_toggles = repeat_toggles;
+    }
+
+    pub fn caps_shift_passthrough(&self) -> bool {
+        self.caps_shift_passthrough
+    }
+
+    pub fn set_caps_shift_passthrough(&mut self, passthrough: bool) {
+        self.caps_shift_passthrough = passthrough;
+    }
+
+    pub fn rules(&self) -> &[Rule] {
+        &self.rules
+    }
+
+    pub fn set_rules(&mut self, rules: Vec<Rule>) {
+        self.rules = rules;
+        self.rule_match = None;
+    }
+
+    /// 마지막 트리거 다운에 적용된 규칙 (탭 모드에서는 키 업의 동작에도 그대로 쓰인다)
+    pub fn rule_match(&self) -> Option<&RuleMatch> {
+        self.rule_match.as_ref()
+    }
+
+    pub fn stats(&self) -> Stats {
+        self.stats
+    }
+
+    pub fn is_learning(&self) -> bool {
+        self.learning
+    }
+
+    /// 탭/홀드 판정을 기다리는 트리거 누름이 있는지
+    pub fn is_pending(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    /// 다음 키(또는 수식 키 조합)를 트리거 후보로 캡처한다
+    pub fn start_learning(&mut self) {
+        self.settle_pending();
+        self.learning = true;
+        self.learn_candidate = None;
+    }
+
+    pub fn cancel_learning(&mut self) {
+        self.learning = false;
+        self.learn_candidate = None;
+    }
+
+    /// 트리거 설정이 바뀌면 판정 대기 중인 누름을 버린다. 다운은 이미 차단했으므로
+    /// 키 업도 차단되고, 홀드로 확정돼 키 다운을 주입한 누름은 키 업에서 그 짝을 떼도록 남긴다.
+    fn settle_pending(&mut self) {
+        if matches!(self.pending, Some(p) if !p.held) {
+            self.pending = None;
+        }
+    }
+
+    /// 눌림 상태를 모두 잊고 (수식 키 고착 복구), 홀드로 주입해 둔 키 다운이 있으면
+    /// 그 키 업을 돌려준다. 호출하는 쪽이 키 업을 보낼 `released` 키와 돌려준 키 업은
+    /// 다운 차단 표시를 지우고, 나머지 표시는 남겨서 나중에 오는 키 업도 차단한다.
+    pub fn release_all(&mut self, released: &[u32]) -> Vec<Stroke> {
+        let strokes = match self.pending.take() {
+            Some(pending) if pending.held => vec![pending.hold_stroke(KeyKind::Up)],
+            _ => Vec::new(),
+        };
+        for vk in released.iter().chain(strokes.iter().map(|s| &s.vk)) {
+            self.swallowed[(vk & 0xFF) as usize] = false;
+        }
+        self.last_down = [None; 256];
+        self.learn_candidate = None;
+        strokes
+    }
+
+    /// Windows는 눌려 있다고 보는데(`is_down`) 엔진은 다운을 보지 못한 수식 키.
+    /// 키 업이 다른 곳으로 가 버려 고착된 키다. 홀드로 주입해 둔 키는 뺀다.
+    pub fn stray_modifiers(&self, is_down: impl Fn(u32) -> bool) -> Vec<u32> {
+        let injected = self
+            .pending
+            .filter(|p| p.held)
+            .map(|p| p.hold_stroke(KeyKind::Down).vk);
+        Modifiers::VKS
+            .iter()
+            .map(|&(vk, _)| vk)
+            .filter(|&vk| {
+                self.last_down[vk as usize].is_none() && Some(vk) != injected && is_down(vk)
+            })
+            .collect()
+    }
+
+    /// Synergy 화면 재진입인지: 주입된 입력이 `REENTRY_GAP_MS` 넘게 없었거나 그사이
+    /// 로컬 키보드를 쓰다가 다시 주입된 입력이 오면 `true`. 키 이벤트마다 `process`보다 먼저 부른다.
+    pub fn reentered(&mut self, ev: &KeyEvent) -> bool {
+        match ev.origin {
+            Origin::Own => false,
+            Origin::Local => {
+                self.local_since_injected = true;
+                false
+            }
+            Origin::Injected => {
+                let recent = matches!(
+                    self.last_injected,
+                    Some(t) if ev.time.wrapping_sub(t) < REENTRY_GAP_MS
+                );
+                let reentered = !recent || self.local_since_injected;
+                self.last_injected = Some(ev.time);
+                self.local_since_injected = false;
+                reentered
+            }
+        }
+    }
+
+    /// 현재 눌려 있는 수식 키
+    pub fn held_modifiers(&self) -> Modifiers {
+        Modifiers::VKS
+            .iter()
+            .filter(|(vk, _)| self.last_down[*vk as usize].is_some())
+            .fold(Modifiers::NONE, |acc, (_, m)| acc | *m)
+    }
+
+    /// 이벤트와 일치하는 트리거 (수식 키 상태, 스캔 코드 포함).
+    /// 트리거가 요구하지 않은 종류의 수식 키가 눌려 있으면 맞지 않는다.
+    /// 여러 개가 일치하면 수식 키를 더 많이 요구하는 쪽, 그다음 스캔 코드를
+    /// 지정한 쪽을 고른다.
+    pub fn lookup(&self, ev: &KeyEvent) -> Option<Binding> {
+        // 수식 키인 트리거 키 자신(Right Alt 등)은 함께 눌린 수식 키로 치지 않는다
+        let own = Modifiers::from_vk(ev.vk).unwrap_or(Modifiers::NONE);
+        let held = self.held_modifiers() & !own;
+        let scan = ev.scan_code();
+        self.bindings
+            .iter()
+            .filter(|b| {
+                let held = if self.caps_ignores_shift(b.trigger) {
+                    held & !Modifiers::SHIFT
+                } else {
+                    held
+                };
+                b.source.accepts(ev.origin)
+                    && b.trigger.matches_key(ev.vk, scan)
+                    && b.trigger.mods.satisfied_by(held)
+            })
+            .max_by_key(|b| {
+                (
+                    b.trigger.mods.count(),
+                    b.trigger.scan.is_some(),
+                    b.source != Source::Any,
+                )
+            })
+            .copied()
+    }
+
+    /// Shift+Caps Lock 통과를 끄면 Shift를 요구하지 않는 Caps Lock 트리거는 Shift가 더 눌려
+    /// 있어도 동작한다 (켜면 다른 트리거처럼 맞지 않아 원래 Caps Lock으로 통과)
+    fn caps_ignores_shift(&self, trigger: Trigger) -> bool {
+        !self.caps_shift_passthrough
+            && trigger.key == VK_CAPITAL
+            && (trigger.mods & Modifiers::SHIFT).is_empty()
+    }
+
+    fn swallow_down(&mut self, vk: u32) {
+        self.swallowed[(vk & 0xFF) as usize] = true;
+    }
+
+    /// 다운을 Windows로 통과시킨다. 키 업을 놓쳐 차단 표시가 남아 있었으면 지운다
+    /// (남겨 두면 Windows가 본 이 다운의 키 업을 차단해 키가 고착된다).
+    fn pass_down(&mut self, vk: u32, repeat: bool) -> Decision {
+        if !repeat {
+            self.swallowed[(vk & 0xFF) as usize] = false;
+        }
+        Decision::Pass
+    }
+
+    /// 차단했던 다운의 짝인 키 업이면 표시를 지우고 `true`
+    fn take_swallowed(&mut self, vk: u32) -> bool {
+        std::mem::take(&mut self.swallowed[(vk & 0xFF) as usize])
+    }
+
+    /// 키별 눌림 상태를 갱신하고, 이미 눌려 있던 키의 다운(자동 반복)인지 돌려준다
+    fn track(&mut self, ev: &KeyEvent) -> bool {
+        let slot = &mut self.last_down[(ev.vk & 0xFF) as usize];
+        match ev.kind {
+            KeyKind::Down => {
+                let repeat = matches!(*slot, Some(t) if ev.time.wrapping_sub(t) < REPEAT_GAP_MS);
+                *slot = Some(ev.time);
+                repeat
+            }
+            KeyKind::Up => {
+                *slot = None;
+                false
+            }
+        }
+    }
+
+    pub fn process(&mut self, ev: &KeyEvent) -> Decision {
+        self.process_in(ev, || None)
+    }
+
+    /// 앱별 규칙을 적용해 처리한다. `window`는 트리거가 눌렸고 규칙이 있을 때만 부른다
+    /// (포그라운드 윈도우 조회 비용을 일반 키에서는 치르지 않도록).
+    pub fn process_in(
+        &mut self,
+        ev: &KeyEvent,
+        window: impl FnOnce() -> Option<Window>,
+    ) -> Decision {
+        // 자신이 주입한 입력은 학습·반복 추적에서도 제외
+        if ev.origin == Origin::Own {
+            return Decision::Pass;
+        }
+
+        let repeat = self.track(ev);
+
+        // 홀드로 주입한 다운은 학습 중에도 키 업에서 떼야 한다
+        if let Some(pending) = self.pending {
+            return self.process_pending(ev, pending, repeat);
+        }
+
+        if self.learning {
+            return self.process_learning(ev);
+        }
+
+        match ev.kind {
+            KeyKind::Down => {
+                self.rule_match = None;
+                // 다운을 차단한 키의 자동 반복은 그사이 트리거가 바뀌었어도 계속 차단한다
+                let swallowed = repeat && self.swallowed[(ev.vk & 0xFF) as usize];
+                let binding = if self.enabled { self.lookup(ev) } else { None };
+                let binding = binding.and_then(|b| self.apply_rules(b, window));
+                if let Some(binding) = binding {
+                    return match self.mode {
+                        TriggerMode::Press if repeat && !self.repeat_toggles => {
+                            self.stats.suppressed_repeats += 1;
+                            Decision::Swallow
+                        }
+                        TriggerMode::Press => {
+                            // 통과시킨 다운의 반복이면 키 업도 통과시켜야 짝이 맞는다
+                            if !repeat {
+                                self.swallow_down(ev.vk);
+                            }
+                            Decision::Emit(binding.action)
+                        }
+                        TriggerMode::Tap if repeat => {
+                            self.stats.suppressed_repeats += 1;
+                            Decision::Swallow
+                        }
+                        TriggerMode::Tap => {
+                            self.swallow_down(ev.vk);
+                            self.pending = Some(Pending {
+                                down: *ev,
+                                action: binding.action,
+                                tap_hold: self.tap_hold,
+                                held: false,
+                            });
+                            Decision::Swallow
+                        }
+                    };
+                }
+                if swallowed {
+                    self.stats.suppressed_repeats += 1;
+                    return Decision::Swallow;
+                }
+                return self.pass_down(ev.vk, repeat);
+            }
+            KeyKind::Up => {
+                // 수식 키를 먼저 떼도 메인 키 업은 다운과 짝을 맞춰 차단
+                if self.take_swallowed(ev.vk) {
+                    return Decision::Swallow;
+                }
+            }
+        }
+
+        Decision::Pass
+    }
+
+    /// 포그라운드 윈도우에 맞는 규칙으로 바인딩을 바꾼다. 규칙이 트리거를 끄면 `None`
+    fn apply_rules(
+        &mut self,
+        binding: Binding,
+        window: impl FnOnce() -> Option<Window>,
+    ) -> Option<Binding> {
+        if self.rules.is_empty() {
+            return Some(binding);
+        }
+        let Some(window) = window() else {
+            return Some(binding);
+        };
+        let Some((index, rule)) = rules::find(&self.rules, &window, binding.trigger) else {
+            return Some(binding);
+        };
+        let rule = rule.clone();
+        let result = (!rule.disable).then(|| Binding {
+            action: rule.action.unwrap_or(binding.action),
+            ..binding
+        });
+        self.rule_match = Some(RuleMatch {
+            index,
+            rule,
+            window,
+        });
+        result
+    }
+
+    /// 학습 모드: 일반 키는 누르는 순간 그때 눌린 수식 키와 함께 캡처하고,
+    /// 수식 키는 단독으로 눌렀다 뗄 때 캡처한다 (예: Ctrl+Right Alt, Right Alt).
+    /// 학습 중의 키 이벤트는 모두 차단한다.
+    fn process_learning(&mut self, ev: &KeyEvent) -> Decision {
+        let held = self.held_modifiers();
+        match ev.kind {
+            KeyKind::Down => {
+                self.swallow_down(ev.vk);
+                if let Some(m) = Modifiers::from_vk(ev.vk) {
+                    self.learn_candidate = Some(ev.learned_trigger(held & !m));
+                    return Decision::Swallow;
+                }
+                self.cancel_learning();
+                Decision::Learned(ev.learned_trigger(held))
+            }
+            KeyKind::Up => {
+                let swallowed = self.take_swallowed(ev.vk);
+                match self.learn_candidate {
+                    Some(candidate) if candidate.key == ev.vk => {
+                        self.cancel_learning();
+                        Decision::Learned(candidate)
+                    }
+                    // 학습 전에 눌려 Windows가 다운을 본 키는 업도 보내야 한다
+                    _ if swallowed => Decision::Swallow,
+                    _ => Decision::Pass,
+                }
+            }
+        }
+    }
+
+    /// 탭/홀드 모드에서 트리거가 눌린 동안의 이벤트 처리
+    fn process_pending(&mut self, ev: &KeyEvent, pending: Pending, repeat: bool) -> Decision {
+        let TapHold { hold_ms, hold } = pending.tap_hold;
+        let hold_stroke = |kind| pending.hold_stroke(kind);
+        // 훅 타임스탬프는 약 49일마다 한 바퀴 돈다
+        let elapsed = ev.time.wrapping_sub(pending.down.time);
+
+        if ev.vk != pending.down.vk {
+            if ev.is_down() && !pending.held {
+                // 트리거를 누른 채 다른 키 → 홀드 확정 (AltGr 조합 등)
+                self.pending = Some(Pending {
+                    held: true,
+                    ..pending
+                });
+                self.pass_down(ev.vk, repeat);
+                return Decision::Replay(vec![
+                    hold_stroke(KeyKind::Down),
+                    Stroke::replay(ev, KeyKind::Down),
+                ]);
+            }
+            if ev.is_down() {
+                return self.pass_down(ev.vk, repeat);
+            }
+            if self.take_swallowed(ev.vk) {
+                return Decision::Swallow;
+            }
+            return Decision::Pass;
+        }
+
+        match ev.kind {
+            KeyKind::Down if pending.held => match hold {
+                HoldAction::PassThrough => Decision::Pass,
+                HoldAction::Modifier(_) => {
+                    self.stats.suppressed_repeats += 1;
+                    Decision::Swallow
+                }
+            },
+            KeyKind::Down if elapsed >= hold_ms => {
+                self.pending = Some(Pending {
+                    held: true,
+                    ..pending
+                });
+                Decision::Replay(vec![hold_stroke(KeyKind::Down)])
+            }
+            KeyKind::Down => {
+                self.stats.suppressed_repeats += 1;
+                Decision::Swallow
+            }
+            KeyKind::Up => {
+                self.pending = None;
+                self.take_swallowed(ev.vk);
+                if pending.held {
+                    match hold {
+                        HoldAction::PassThrough => Decision::Pass,
+                        HoldAction::Modifier(_) => Decision::Replay(vec![hold_stroke(KeyKind::Up)]),
+                    }
+                } else if elapsed < hold_ms {
+                    Decision::Emit(pending.action)
+                } else {
+                    Decision::Replay(vec![hold_stroke(KeyKind::Down), hold_stroke(KeyKind::Up)])
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VK_A: u32 = 0x41;
+    const VK_LMENU: u32 = 0xA4;
+    const VK_RMENU: u32 = 0xA5;
+    const VK_LCONTROL: u32 = 0xA2;
+    const VK_RCONTROL: u32 = 0xA3;
+    const VK_LSHIFT: u32 = 0xA0;
+    const VK_RSHIFT: u32 = 0xA1;
+    const VK_SPACE: u32 = 0x20;
+    const VK_F13: u32 = 0x7C;
+
+    #[test]
+    fn trigger_down_emits_and_up_is_swallowed() {
+        let mut engine = Engine::default();
+        assert_eq!(
+            engine.process(&KeyEvent::down(VK_CAPITAL)),
+            Decision::Emit(Action::ToggleHangul)
+        );
+        assert_eq!(engine.process(&KeyEvent::up(VK_CAPITAL)), Decision::Swallow);
+    }
+
+    #[test]
+    fn other_keys_pass() {
+        let mut engine = Engine::default();
+        assert_eq!(engine.process(&KeyEvent::down(VK_A)), Decision::Pass);
+        assert_eq!(engine.process(&KeyEvent::up(VK_A)), Decision::Pass);
+    }
+
+    #[test]
+    fn disabled_engine_passes_trigger() {
+        let mut engine = Engine::default();
+        engine.set_enabled(false);
+        assert_eq!(engine.process(&KeyEvent::down(VK_CAPITAL)), Decision::Pass);
+        assert_eq!(engine.process(&KeyEvent::up(VK_CAPITAL)), Decision::Pass);
+    }
+
+    fn terminal() -> Option<Window> {
+        Some(Window {
+            process: "WindowsTerminal.exe".to_string(),
+            class: "CASCADIA_HOSTING_WINDOW_CLASS".to_string(),
+        })
+    }
+
+    #[test]
+    fn rule_disables_trigger_in_matching_app() {
+        let mut engine = Engine::default();
+        engine.set_rules(vec![Rule {
+            process: Some("WindowsTerminal.exe".to_string()),
+            disable: true,
+            ..Rule::default()
+        }]);
+        let down = KeyEvent::down(VK_CAPITAL);
+        assert_eq!(engine.process_in(&down, terminal), Decision::Pass);
+        assert_eq!(engine.rule_match().map(|m| m.index), Some(0));
+        assert_eq!(
+            engine.process_in(&KeyEvent::up(VK_CAPITAL).at(50), terminal),
+            Decision::Pass
+        );
+
+        // 다른 앱에서는 그대로 동작하고, 일반 키는 윈도우를 묻지 않는다
+        let other = || {
+            Some(Window {
+                process: "notepad.exe".to_string(),
+                class: "Notepad".to_string(),
+            })
+        };
+        let down = KeyEvent::down(VK_CAPITAL).at(100);
+        assert_eq!(
+            engine.process_in(&down, other),
+            Decision::Emit(Action::ToggleHangul)
+        );
+        assert_eq!(engine.rule_match(), None);
+        let no_lookup = || -> Option<Window> { panic!("window looked up for a plain key") };
+        assert_eq!(
+            engine.process_in(&KeyEvent::down(VK_A).at(200), no_lookup),
+            Decision::Pass
+        );
+    }
+
+    #[test]
+    fn rule_replaces_action_in_tap_mode() {
+        let mut engine = Engine::default();
+        engine.set_mode(TriggerMode::Tap);
+        engine.set_rules(vec![Rule {
+            class: Some("cascadia_hosting_window_class".to_string()),
+            action: Some(Action::SetEnglish),
+            method: rules::Method::SendInput,
+            ..Rule::default()
+        }]);
+        assert_eq!(
+            engine.process_in(&KeyEvent::down(VK_CAPITAL), terminal),
+            Decision::Swallow
+        );
+        assert_eq!(
+            engine.process_in(&KeyEvent::up(VK_CAPITAL).at(50), terminal),
+            Decision::Emit(Action::SetEnglish)
+        );
+        assert_eq!(
+            engine.rule_match().map(|m| m.rule.method),
+            Some(rules::Method::SendInput)
+        );
+    }
+
+    #[test]
+    fn learning_captures_next_key_and_swallows_ups() {
+        let mut engine = Engine::default();
+        engine.start_learning();
+        // 학습 전에 눌린 키의 업은 Windows가 다운을 봤으므로 통과
+        assert_eq!(engine.process(&KeyEvent::up(VK_A)), Decision::Pass);
+
+        assert_eq!(
+            engine.process(&KeyEvent::down(VK_F13)),
+            Decision::Learned(Trigger::single(VK_F13))
+        );
+        assert!(!engine.is_learning());
+        assert_eq!(engine.process(&KeyEvent::up(VK_F13)), Decision::Swallow);
+
+        engine.set_bindings(vec![Binding::new(
+            Trigger::single(VK_F13),
+            Action::ToggleHangul,
+        )]);
+        assert_eq!(
+            engine.process(&KeyEvent::down(VK_F13)),
+            Decision::Emit(Action::ToggleHangul)
+        );
+    }
+
+    #[test]
+    fn learning_lone_modifier_captures_on_release() {
+        let mut engine = Engine::default();
+        engine.start_learning();
+        assert_eq!(engine.process(&KeyEvent::down(VK_LMENU)), Decision::Swallow);
+        assert!(engine.is_learning());
+        assert_eq!(
+            engine.process(&KeyEvent::up(VK_LMENU)),
+            Decision::Learned(Trigger::single(VK_LMENU))
+        );
+    }
+
+    #[test]
+    fn learning_captures_chord() {
+        let mut engine = Engine::default();
+        engine.start_learning();
+        engine.process(&KeyEvent::down(VK_LCONTROL));
+        engine.process(&KeyEvent::down(VK_RMENU));
+        assert_eq!(
+            engine.process(&KeyEvent::up(VK_RMENU)),
+            Decision::Learned(Trigger::chord(Modifiers::LCTRL, VK_RMENU))
+        );
+
+        engine.start_learning();
+        engine.process(&KeyEvent::down(VK_RSHIFT));
+        assert_eq!(
+            engine.process(&KeyEvent::down(VK_SPACE)),
+            Decision::Learned(Trigger::chord(
+                Modifiers::RSHIFT | Modifiers::LCTRL,
+                VK_SPACE
+            ))
+        );
+    }
+
+    #[test]
+    fn chord_requires_modifier() {
+        let mut engine = Engine::default();
+        engine.set_bindings(vec![Binding::new(
+            Trigger::chord(Modifiers::SHIFT, VK_SPACE),
+            Action::ToggleHangul,
+        )]);
+
+        assert_eq!(engine.process(&KeyEvent::down(VK_SPACE)), Decision::Pass);
+        assert_eq!(engine.process(&KeyEvent::up(VK_SPACE)), Decision::Pass);
+
+        assert_eq!(engine.process(&KeyEvent::down(VK_RSHIFT)), Decision::Pass);
+        assert_eq!(
+            engine.process(&KeyEvent::down(VK_SPACE)),
+            Decision::Emit(Action::ToggleHangul)
+        );
+        // Shift를 먼저 떼도 Space 업은 차단
+        assert_eq!(engine.process(&KeyEvent::up(VK_RSHIFT)), Decision::Pass);
+        assert_eq!(engine.process(&KeyEvent::up(VK_SPACE)), Decision::Swallow);
+    }
+
+    #[test]
+    fn extra_modifiers_are_shortcuts_not_triggers() {
+        let mut engine = Engine::default();
+        engine.set_bindings(vec![
+            Binding::new(Trigger::single(VK_SPACE), Action::ToggleHangul),
+            Binding::new(Trigger::single(VK_RMENU), Action::SetHangul),
+            Binding::new(
+                Trigger::chord(Modifiers::SHIFT, VK_SPACE),
+                Action::SetEnglish,
+            ),
+        ]);
+
+        // 요구하지 않은 Ctrl이 눌려 있으면 Ctrl+Space 단축키로 그대로 보낸다
+        engine.process(&KeyEvent::down(VK_LCONTROL));
+        assert_eq!(engine.process(&KeyEvent::down(VK_SPACE)), Decision::Pass);
+        assert_eq!(engine.process(&KeyEvent::up(VK_SPACE)), Decision::Pass);
+        assert_eq!(engine.process(&KeyEvent::down(VK_RMENU)), Decision::Pass);
+        assert_eq!(engine.process(&KeyEvent::up(VK_RMENU)), Decision::Pass);
+        engine.process(&KeyEvent::up(VK_LCONTROL));
+
+        // Shift+Ctrl+Space는 Shift+Space 트리거도 아니다
+        engine.process(&KeyEvent::down(VK_LSHIFT));
+        engine.process(&KeyEvent::down(VK_LCONTROL));
+        assert_eq!(engine.process(&KeyEvent::down(VK_SPACE)), Decision::Pass);
+        engine.process(&KeyEvent::up(VK_SPACE));
+        engine.process(&KeyEvent::up(VK_LCONTROL));
+        assert_eq!(
+            engine.process(&KeyEvent::down(VK_SPACE)),
+            Decision::Emit(Action::SetEnglish)
+        );
+        engine.process(&KeyEvent::up(VK_SPACE));
+        engine.process(&KeyEvent::up(VK_LSHIFT));
+
+        // 수식 키인 트리거 키 자신은 함께 눌린 수식 키가 아니다
+        assert_eq!(
+            engine.process(&KeyEvent::down(VK_RMENU)),
+            Decision::Emit(Action::SetHangul)
+        );
+    }
+
+    #[test]
+    fn multiple_triggers_are_all_active() {
+        let mut engine = Engine::default();
+        engine.bind(Binding::new(
+            Trigger::single(VK_RMENU),
+            Action::ToggleHangul,
+        ));
+        assert_eq!(engine.bindings().len(), 2);
+
+        for vk in [VK_CAPITAL, VK_RMENU] {
+            assert_eq!(
+                engine.process(&KeyEvent::down(vk)),
+                Decision::Emit(Action::ToggleHangul)
+            );
+            assert_eq!(engine.process(&KeyEvent::up(vk)), Decision::Swallow);
+        }
+
+        assert!(engine.unbind(Trigger::single(VK_CAPITAL)));
+        assert_eq!(engine.process(&KeyEvent::down(VK_CAPITAL)), Decision::Pass);
+    }
+
+    #[test]
+    fn most_specific_trigger_wins() {
+        let mut engine = Engine::default();
+        let plain = Binding::new(Trigger::single(VK_SPACE), Action::ToggleHangul);
+        let chord = Binding::new(
+            Trigger::chord(Modifiers::SHIFT, VK_SPACE),
+            Action::ToggleHangul,
+        );
+        engine.set_bindings(vec![plain, chord]);
+
+        engine.process(&KeyEvent::down(VK_RSHIFT));
+        assert_eq!(engine.lookup(&KeyEvent::down(VK_SPACE)), Some(chord));
+        engine.process(&KeyEvent::up(VK_RSHIFT));
+        assert_eq!(engine.lookup(&KeyEvent::down(VK_SPACE)), Some(plain));
+    }
+
+    #[test]
+    fn scan_code_trigger_distinguishes_physical_key() {
+        // Synergy가 Right Alt를 0xA4 + 확장 플래그로 보내는 경우
+        let relayed_ralt = ScanCode::new(0x38, true);
+        let mut engine = Engine::default();
+        engine.set_bindings(vec![Binding::new(
+            Trigger::single(VK_LMENU).with_scan(relayed_ralt),
+            Action::ToggleHangul,
+        )]);
+
+        let left_alt = KeyEvent::down(VK_LMENU).with_scan(0x38, false);
+        assert_eq!(engine.process(&left_alt), Decision::Pass);
+        engine.process(&KeyEvent::up(VK_LMENU).with_scan(0x38, false));
+
+        let right_alt = KeyEvent::down(VK_LMENU).with_scan(0x38, true);
+        assert_eq!(
+            engine.process(&right_alt),
+            Decision::Emit(Action::ToggleHangul)
+        );
+        assert_eq!(
+            engine.process(&KeyEvent::up(VK_LMENU).with_scan(0x38, true)),
+            Decision::Swallow
+        );
+    }
+
+    #[test]
+    fn scan_code_trigger_beats_vk_only_trigger() {
+        let vk_only = Binding::new(Trigger::single(VK_LMENU), Action::SetEnglish);
+        let physical = Binding::new(
+            Trigger::single(VK_LMENU).with_scan(ScanCode::new(0x38, true)),
+            Action::SetHangul,
+        );
+        let mut engine = Engine::default();
+        engine.set_bindings(vec![vk_only, physical]);
+
+        let ev = KeyEvent::down(VK_LMENU).with_scan(0x38, true);
+        assert_eq!(engine.lookup(&ev), Some(physical));
+        let ev = KeyEvent::down(VK_LMENU).with_scan(0x38, false);
+        assert_eq!(engine.lookup(&ev), Some(vk_only));
+    }
+
+    #[test]
+    fn learning_captures_scan_code_and_extended_flag() {
+        let mut engine = Engine::default();
+        engine.start_learning();
+        engine.process(&KeyEvent::down(VK_LMENU).with_scan(0x38, true));
+        assert_eq!(
+            engine.process(&KeyEvent::up(VK_LMENU).with_scan(0x38, true)),
+            Decision::Learned(Trigger::single(VK_LMENU).with_scan(ScanCode::new(0x38, true)))
+        );
+
+        engine.start_learning();
+        assert_eq!(
+            engine.process(&KeyEvent::down(VK_F13).with_scan(0x64, false)),
+            Decision::Learned(Trigger::single(VK_F13).with_scan(ScanCode::new(0x64, false)))
+        );
+    }
+
+    #[test]
+    fn origin_from_flags_and_signature() {
+        assert_eq!(Origin::classify(0, INJECT_SIGNATURE), Origin::Local);
+        assert_eq!(Origin::classify(LLKHF_INJECTED, 0), Origin::Injected);
+        assert_eq!(
+            Origin::classify(LLKHF_INJECTED | LLKHF_LOWER_IL_INJECTED, 0),
+            Origin::Injected
+        );
+        assert_eq!(
+            Origin::classify(LLKHF_INJECTED, INJECT_SIGNATURE),
+            Origin::Own
+        );
+    }
+
+    #[test]
+    fn shift_caps_lock_passes_through_when_enabled() {
+        let mut engine = Engine::default();
+        engine.set_bindings(vec![
+            Binding::new(Trigger::single(VK_CAPITAL), Action::ToggleHangul),
+            Binding::new(
+                Trigger::chord(Modifiers::CTRL, VK_CAPITAL),
+                Action::ConvertHanja,
+            ),
+        ]);
+
+        // 꺼져 있으면 Shift가 눌려 있어도 트리거
+        engine.process(&KeyEvent::down(VK_LSHIFT));
+        assert_eq!(
+            engine.process(&KeyEvent::down(VK_CAPITAL)),
+            Decision::Emit(Action::ToggleHangul)
+        );
+        assert_eq!(engine.process(&KeyEvent::up(VK_CAPITAL)), Decision::Swallow);
+
+        // 켜면 Shift+Caps Lock은 원래 Caps Lock (다운과 업 모두 통과)
+        engine.set_caps_shift_passthrough(true);
+        assert_eq!(
+            engine.process(&KeyEvent::down(VK_CAPITAL).at(2000)),
+            Decision::Pass
+        );
+        assert_eq!(engine.process(&KeyEvent::up(VK_CAPITAL)), Decision::Pass);
+
+        // Shift 없이 누르거나 다른 수식 키 조합은 그대로 트리거
+        engine.process(&KeyEvent::up(VK_LSHIFT));
+        assert_eq!(
+            engine.process(&KeyEvent::down(VK_CAPITAL).at(4000)),
+            Decision::Emit(Action::ToggleHangul)
+        );
+        engine.process(&KeyEvent::up(VK_CAPITAL));
+        engine.process(&KeyEvent::down(VK_LCONTROL));
+        assert_eq!(
+            engine.process(&KeyEvent::down(VK_CAPITAL).at(6000)),
+            Decision::Emit(Action::ConvertHanja)
+        );
+
+        // Shift를 요구하는 Caps Lock 트리거는 통과시키지 않는다
+        engine.bind(Binding::new(
+            Trigger::chord(Modifiers::SHIFT, VK_CAPITAL),
+            Action::SetEnglish,
+        ));
+        engine.process(&KeyEvent::up(VK_CAPITAL));
+        engine.process(&KeyEvent::up(VK_LCONTROL));
+        engine.process(&KeyEvent::down(VK_RSHIFT));
+        assert_eq!(
+            engine.process(&KeyEvent::down(VK_CAPITAL).at(8000)),
+            Decision::Emit(Action::SetEnglish)
+        );
+    }
+
+    #[test]
+    fn injected_only_trigger_leaves_local_key_alone() {
+        let mut engine = Engine::default();
+        engine.set_bindings(vec![Binding::new(
+            Trigger::single(VK_CAPITAL),
+            Action::ToggleHangul,
+        )
+        .with_source(Source::Injected)]);
+
+        // 로컬 Caps Lock은 원래 기능 그대로
+        let local = KeyEvent::down(VK_CAPITAL);
+        assert_eq!(engine.process(&local), Decision::Pass);
+        assert_eq!(engine.process(&KeyEvent::up(VK_CAPITAL)), Decision::Pass);
+
+        // Synergy가 중계한 Caps Lock은 한/영 전환
+        let relayed = KeyEvent::down(VK_CAPITAL).with_origin(Origin::Injected);
+        assert_eq!(
+            engine.process(&relayed),
+            Decision::Emit(Action::ToggleHangul)
+        );
+        let relayed_up = KeyEvent::up(VK_CAPITAL).with_origin(Origin::Injected);
+        assert_eq!(engine.process(&relayed_up), Decision::Swallow);
+    }
+
+    #[test]
+    fn own_injections_never_trigger() {
+        let mut engine = Engine::default();
+        let own = KeyEvent::down(VK_CAPITAL).with_origin(Origin::Own);
+        assert_eq!(engine.process(&own), Decision::Pass);
+        engine.start_learning();
+        assert_eq!(engine.process(&own), Decision::Pass);
+        assert!(engine.is_learning());
+    }
+
+    #[test]
+    fn source_specific_bindings_coexist() {
+        let local =
+            Binding::new(Trigger::single(VK_RMENU), Action::SetEnglish).with_source(Source::Local);
+        let relayed = Binding::new(Trigger::single(VK_RMENU), Action::SetHangul)
+            .with_source(Source::Injected);
+        let mut engine = Engine::new();
+        engine.bind(local);
+        engine.bind(relayed);
+        assert_eq!(engine.bindings(), &[local, relayed]);
+        assert!(engine.remove_binding(Binding {
+            action: Action::ToggleHangul,
+            ..local
+        }));
+        assert_eq!(engine.bindings(), &[relayed]);
+        engine.bind(local);
+
+        let ev = KeyEvent::down(VK_RMENU);
+        assert_eq!(engine.lookup(&ev), Some(local));
+        assert_eq!(
+            engine.lookup(&ev.with_origin(Origin::Injected)),
+            Some(relayed)
+        );
+    }
+
+    #[test]
+    fn binding_parse_and_display() {
+        let b: Binding = "Ctrl+0xA5, toggle_hangul".parse().unwrap();
+        assert_eq!(
+            b,
+            Binding::new(Trigger::chord(Modifiers::CTRL, 0xA5), Action::ToggleHangul)
+        );
+        assert_eq!(b.to_string().parse::<Binding>(), Ok(b));
+        // 동작을 생략하면 한/영 전환
+        assert_eq!(
+            "0x14".parse::<Binding>().map(|b| b.action),
+            Ok(Action::ToggleHangul)
+        );
+        assert!("0x14, launch_rockets".parse::<Binding>().is_err());
+
+        let b: Binding = "0x14, set_hangul, injected".parse().unwrap();
+        assert_eq!(b.source, Source::Injected);
+        assert_eq!(b.to_string(), "CapsLock, set_hangul, injected");
+        assert_eq!(b.to_string().parse::<Binding>(), Ok(b));
+        assert!("0x14, set_hangul, bluetooth".parse::<Binding>().is_err());
+    }
+
+    #[test]
+    fn set_actions_force_native_bit() {
+        const FULLSHAPE: u32 = 0x0008;
+        let hangul = IME_CMODE_NATIVE | FULLSHAPE;
+        let english = FULLSHAPE;
+
+        assert_eq!(Action::ToggleHangul.apply_conversion(hangul), Some(english));
+        assert_eq!(Action::ToggleHangul.apply_conversion(english), Some(hangul));
+        for current in [hangul, english] {
+            assert_eq!(Action::SetHangul.apply_conversion(current), Some(hangul));
+            assert_eq!(Action::SetEnglish.apply_conversion(current), Some(english));
+        }
+    }
+
+    #[test]
+    fn hanja_is_not_a_conversion_mode_change() {
+        for current in [0, IME_CMODE_NATIVE] {
+            assert_eq!(Action::ConvertHanja.apply_conversion(current), None);
+        }
+    }
+
+    #[test]
+    fn action_names_round_trip() {
+        for action in Action::ALL {
+            assert_eq!(action.name().parse(), Ok(action));
+        }
+    }
+
+    #[test]
+    fn chord_respects_modifier_side() {
+        let mut engine = Engine::default();
+        engine.set_bindings(vec![Binding::new(
+            Trigger::chord(Modifiers::LCTRL, VK_RMENU),
+            Action::ToggleHangul,
+        )]);
+
+        engine.process(&KeyEvent::down(VK_RCONTROL));
+        assert_eq!(engine.process(&KeyEvent::down(VK_RMENU)), Decision::Pass);
+        engine.process(&KeyEvent::up(VK_RMENU));
+        engine.process(&KeyEvent::up(VK_RCONTROL));
+
+        engine.process(&KeyEvent::down(VK_LCONTROL));
+        assert_eq!(
+            engine.process(&KeyEvent::down(VK_RMENU)),
+            Decision::Emit(Action::ToggleHangul)
+        );
+    }
+
+    #[test]
+    fn autorepeat_only_first_down_counts() {
+        let mut engine = Engine::default();
+        assert_eq!(
+            engine.process(&KeyEvent::down(VK_CAPITAL).at(1000)),
+            Decision::Emit(Action::ToggleHangul)
+        );
+        for t in [1500, 1530, 1560] {
+            assert_eq!(
+                engine.process(&KeyEvent::down(VK_CAPITAL).at(t)),
+                Decision::Swallow
+            );
+        }
+        assert_eq!(engine.stats().suppressed_repeats, 3);
+
+        assert_eq!(
+            engine.process(&KeyEvent::up(VK_CAPITAL).at(1600)),
+            Decision::Swallow
+        );
+        assert_eq!(
+            engine.process(&KeyEvent::down(VK_CAPITAL).at(1700)),
+            Decision::Emit(Action::ToggleHangul)
+        );
+    }
+
+    #[test]
+    fn repeat_toggles_fires_on_every_down() {
+        let mut engine = Engine::default();
+        engine.set_repeat_toggles(true);
+        for t in [1000, 1500, 1530] {
+            assert_eq!(
+                engine.process(&KeyEvent::down(VK_CAPITAL).at(t)),
+                Decision::Emit(Action::ToggleHangul)
+            );
+        }
+        assert_eq!(engine.stats().suppressed_repeats, 0);
+    }
+
+    #[test]
+    fn missed_key_up_does_not_block_next_press() {
+        let mut engine = Engine::default();
+        engine.process(&KeyEvent::down(VK_CAPITAL).at(1000));
+        // 키 업 유실 후 한참 뒤의 새 누름
+        assert_eq!(
+            engine.process(&KeyEvent::down(VK_CAPITAL).at(1000 + REPEAT_GAP_MS)),
+            Decision::Emit(Action::ToggleHangul)
+        );
+    }
+
+    fn tap_mode(hold: HoldAction) -> Engine {
+        let mut engine = Engine::default();
+        engine.set_bindings(vec![Binding::new(
+            Trigger::single(VK_RMENU),
+            Action::ToggleHangul,
+        )]);
+        engine.set_mode(TriggerMode::Tap);
+        engine.set_tap_hold(TapHold { hold_ms: 200, hold });
+        engine
+    }
+
+    #[test]
+    fn quick_tap_emits_on_key_up() {
+        let mut engine = tap_mode(HoldAction::PassThrough);
+        assert_eq!(
+            engine.process(&KeyEvent::down(VK_RMENU).at(1000)),
+            Decision::Swallow
+        );
+        assert!(engine.is_pending());
+        assert_eq!(
+            engine.process(&KeyEvent::up(VK_RMENU).at(1120)),
+            Decision::Emit(Action::ToggleHangul)
+        );
+        assert!(!engine.is_pending());
+    }
+
+    #[test]
+    fn long_hold_replays_original_key() {
+        let mut engine = tap_mode(HoldAction::PassThrough);
+        let down = KeyEvent::down(VK_RMENU).at(1000);
+        engine.process(&down);
+        assert_eq!(
+            engine.process(&KeyEvent::up(VK_RMENU).at(1500)),
+            Decision::Replay(vec![
+                Stroke::replay(&down, KeyKind::Down),
+                Stroke::replay(&down, KeyKind::Up),
+            ])
+        );
+    }
+
+    #[test]
+    fn other_key_while_held_resolves_as_hold() {
+        let mut engine = tap_mode(HoldAction::PassThrough);
+        let down = KeyEvent::down(VK_RMENU).at(1000);
+        engine.process(&down);
+
+        let a = KeyEvent::down(VK_A).at(1050);
+        assert_eq!(
+            engine.process(&a),
+            Decision::Replay(vec![
+                Stroke::replay(&down, KeyKind::Down),
+                Stroke::replay(&a, KeyKind::Down),
+            ])
+        );
+        assert_eq!(engine.process(&KeyEvent::up(VK_A).at(1080)), Decision::Pass);
+        assert_eq!(
+            engine.process(&KeyEvent::up(VK_RMENU).at(1100)),
+            Decision::Pass
+        );
+    }
+
+    #[test]
+    fn hold_as_modifier_substitutes_key() {
+        let mut engine = tap_mode(HoldAction::Modifier(VK_LCONTROL));
+        engine.process(&KeyEvent::down(VK_RMENU).at(1000));
+        assert_eq!(
+            engine.process(&KeyEvent::down(VK_RMENU).at(1300)),
+            Decision::Replay(vec![Stroke::key(VK_LCONTROL, KeyKind::Down)])
+        );
+        assert_eq!(
+            engine.process(&KeyEvent::down(VK_RMENU).at(1330)),
+            Decision::Swallow
+        );
+        assert_eq!(
+            engine.process(&KeyEvent::up(VK_RMENU).at(1400)),
+            Decision::Replay(vec![Stroke::key(VK_LCONTROL, KeyKind::Up)])
+        );
+    }
+
+    #[test]
+    fn tap_threshold_survives_timestamp_wraparound() {
+        let mut engine = tap_mode(HoldAction::PassThrough);
+        engine.process(&KeyEvent::down(VK_RMENU).at(u32::MAX - 50));
+        assert_eq!(
+            engine.process(&KeyEvent::up(VK_RMENU).at(49)),
+            Decision::Emit(Action::ToggleHangul)
+        );
+    }
+
+    #[test]
+    fn own_injection_passes_without_blocking_real_keys() {
+        let mut engine = Engine::default();
+        engine.bind(Binding::new(
+            Trigger::single(VK_RMENU),
+            Action::ToggleHangul,
+        ));
+
+        // 트리거 → 동작 실행 중 (VK_HANGUL 주입)
+        assert_eq!(
+            engine.process(&KeyEvent::down(VK_RMENU)),
+            Decision::Emit(Action::ToggleHangul)
+        );
+        const VK_HANGUL: u32 = 0x15;
+        let own = |ev: KeyEvent| ev.with_origin(Origin::Own);
+        assert_eq!(
+            engine.process(&own(KeyEvent::down(VK_HANGUL))),
+            Decision::Pass
+        );
+
+        // 주입 도중에 들어온 실제 키도 평소처럼 처리된다
+        assert_eq!(engine.process(&KeyEvent::down(VK_A)), Decision::Pass);
+        assert_eq!(engine.process(&KeyEvent::up(VK_RMENU)), Decision::Swallow);
+        assert_eq!(
+            engine.process(&KeyEvent::down(VK_CAPITAL)),
+            Decision::Emit(Action::ToggleHangul)
+        );
+        assert_eq!(engine.process(&KeyEvent::up(VK_CAPITAL)), Decision::Swallow);
+
+        assert_eq!(
+            engine.process(&own(KeyEvent::up(VK_HANGUL))),
+            Decision::Pass
+        );
+    }
+
+    #[test]
+    fn own_replayed_trigger_is_not_caught_again() {
+        let mut engine = Engine::default();
+        engine.set_mode(TriggerMode::Tap);
+        engine.process(&KeyEvent::down(VK_CAPITAL).at(0));
+        assert_eq!(
+            engine.process(&KeyEvent::down(VK_CAPITAL).at(300)),
+            Decision::Replay(vec![Stroke::key(VK_CAPITAL, KeyKind::Down)])
+        );
+        // 재주입된 다운은 훅에 다시 들어와도 그대로 통과
+        let replayed = KeyEvent::down(VK_CAPITAL).with_origin(Origin::Own);
+        assert_eq!(engine.process(&replayed), Decision::Pass);
+        assert_eq!(
+            engine.process(&KeyEvent::up(VK_CAPITAL).at(400)),
+            Decision::Pass
+        );
+    }
+
+    #[test]
+    fn swallowed_down_keeps_its_up_after_trigger_change() {
+        // 누름 모드: 다운을 차단한 뒤 트리거를 빼도 반복과 업은 차단
+        let mut engine = Engine::default();
+        engine.bind(Binding::new(
+            Trigger::single(VK_RMENU),
+            Action::ToggleHangul,
+        ));
+        engine.process(&KeyEvent::down(VK_RMENU));
+        assert!(engine.unbind(Trigger::single(VK_RMENU)));
+        assert_eq!(
+            engine.process(&KeyEvent::down(VK_RMENU).at(30)),
+            Decision::Swallow
+        );
+        assert_eq!(
+            engine.process(&KeyEvent::up(VK_RMENU).at(60)),
+            Decision::Swallow
+        );
+        assert_eq!(
+            engine.process(&KeyEvent::down(VK_RMENU).at(100)),
+            Decision::Pass
+        );
+
+        // 반대로 통과시킨 다운은 도중에 트리거가 돼도 업을 통과
+        engine.process(&KeyEvent::down(VK_RMENU).at(200));
+        engine.bind(Binding::new(
+            Trigger::single(VK_RMENU),
+            Action::ToggleHangul,
+        ));
+        engine.set_repeat_toggles(true);
+        assert_eq!(
+            engine.process(&KeyEvent::down(VK_RMENU).at(230)),
+            Decision::Emit(Action::ToggleHangul)
+        );
+        assert_eq!(
+            engine.process(&KeyEvent::up(VK_RMENU).at(260)),
+            Decision::Pass
+        );
+
+        // 차단한 다운의 키 업을 놓친 채 트리거를 빼면, 다음 누름은 다운과 업 모두 통과
+        engine.process(&KeyEvent::down(VK_RMENU).at(300));
+        assert!(engine.unbind(Trigger::single(VK_RMENU)));
+        let next = 300 + REPEAT_GAP_MS;
+        assert_eq!(
+            engine.process(&KeyEvent::down(VK_RMENU).at(next)),
+            Decision::Pass
+        );
+        assert_eq!(
+            engine.process(&KeyEvent::up(VK_RMENU).at(next + 30)),
+            Decision::Pass
+        );
+
+        // 탭 모드: 판정 대기 중에 트리거를 바꾸면 동작 없이 업만 차단
+        let mut engine = tap_mode(HoldAction::PassThrough);
+        engine.process(&KeyEvent::down(VK_RMENU));
+        engine.set_bindings(vec![Binding::new(
+            Trigger::single(VK_F13),
+            Action::ToggleHangul,
+        )]);
+        assert_eq!(
+            engine.process(&KeyEvent::up(VK_RMENU).at(50)),
+            Decision::Swallow
+        );
+    }
+
+    #[test]
+    fn held_trigger_releases_what_it_injected() {
+        let mut engine = tap_mode(HoldAction::Modifier(VK_LCONTROL));
+        engine.process(&KeyEvent::down(VK_RMENU));
+        engine.process(&KeyEvent::down(VK_RMENU).at(300));
+        // 누르는 도중 트리거와 홀드 설정이 바뀌어도 주입한 Ctrl은 키 업에서 뗀다
+        engine.set_bindings(Vec::new());
+        engine.set_tap_hold(TapHold {
+            hold_ms: 200,
+            hold: HoldAction::PassThrough,
+        });
+        engine.start_learning();
+        assert_eq!(
+            engine.process(&KeyEvent::up(VK_RMENU).at(400)),
+            Decision::Replay(vec![Stroke::key(VK_LCONTROL, KeyKind::Up)])
+        );
+        assert!(engine.is_learning());
+    }
+
+    #[test]
+    fn release_all_forgets_pressed_keys() {
+        let mut engine = tap_mode(HoldAction::Modifier(VK_LCONTROL));
+        engine.process(&KeyEvent::down(VK_RMENU));
+        engine.process(&KeyEvent::down(VK_RMENU).at(300));
+        engine.process(&KeyEvent::down(VK_LSHIFT).at(350));
+
+        // Windows가 누른 것으로 아는 Shift는 엔진도 봤고, 주입한 Ctrl은 홀드 중이다
+        let windows_down = |vk| [VK_LSHIFT, VK_LCONTROL, VK_RCONTROL].contains(&vk);
+        assert_eq!(engine.stray_modifiers(windows_down), vec![VK_RCONTROL]);
+
+        assert_eq!(
+            engine.release_all(&[VK_LSHIFT, VK_LCONTROL]),
+            vec![Stroke::key(VK_LCONTROL, KeyKind::Up)]
+        );
+        assert_eq!(engine.held_modifiers(), Modifiers::NONE);
+        // Windows는 트리거 다운을 본 적이 없으므로 나중에 오는 키 업도 차단
+        assert_eq!(
+            engine.process(&KeyEvent::up(VK_RMENU).at(400)),
+            Decision::Swallow
+        );
+        assert_eq!(
+            engine.process(&KeyEvent::up(VK_LSHIFT).at(450)),
+            Decision::Pass
+        );
+
+        // 홀드로 트리거 다운을 주입해 두었으면 그 키 업을 돌려주므로 나중의 키 업은 통과
+        let mut engine = tap_mode(HoldAction::PassThrough);
+        engine.process(&KeyEvent::down(VK_RMENU));
+        engine.process(&KeyEvent::down(VK_RMENU).at(300));
+        assert_eq!(
+            engine.release_all(&[]),
+            vec![Stroke::replay(&KeyEvent::down(VK_RMENU), KeyKind::Up)]
+        );
+        assert_eq!(
+            engine.process(&KeyEvent::up(VK_RMENU).at(400)),
+            Decision::Pass
+        );
+    }
+
+    #[test]
+    fn reentry_after_gap_or_local_input() {
+        let mut engine = Engine::default();
+        let injected = |time| KeyEvent::down(VK_A).at(time).with_origin(Origin::Injected);
+        assert!(engine.reentered(&injected(0)));
+        assert!(!engine.reentered(&injected(100)));
+        assert!(engine.reentered(&injected(100 + REENTRY_GAP_MS)));
+
+        assert!(!engine.reentered(&KeyEvent::down(VK_A).at(4000)));
+        assert!(!engine.reentered(&KeyEvent::down(VK_A).with_origin(Origin::Own)));
+        assert!(engine.reentered(&injected(4100)));
+    }
+}
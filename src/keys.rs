@@ -0,0 +1,309 @@
+//! 가상 키 코드 이름표
+//!
+//! 설정 파일, 트레이 메뉴, 로그에서 `0xA5` 대신 `RightAlt`처럼 이름으로 키를 쓴다.
+//! 이름은 대소문자를 구분하지 않고, 이름이 없는 키는 `0xNN`으로 읽고 쓴다.
+
+use std::fmt;
+
+use crate::trigger::parse_vk;
+
+/// (가상 키 코드, 표준 이름). 글자·숫자 키(0x30–0x39, 0x41–0x5A)는 [`vk_name`]에서 따로 처리한다.
+const NAMES: &[(u32, &str)] = &[
+    // 마우스
+    (0x01, "LButton"),
+    (0x02, "RButton"),
+    (0x03, "Cancel"),
+    (0x04, "MButton"),
+    (0x05, "XButton1"),
+    (0x06, "XButton2"),
+    // 편집·제어
+    (0x08, "Backspace"),
+    (0x09, "Tab"),
+    (0x0C, "Clear"),
+    (0x0D, "Enter"),
+    (0x10, "Shift"),
+    (0x11, "Ctrl"),
+    (0x12, "Alt"),
+    (0x13, "Pause"),
+    (0x14, "CapsLock"),
+    // IME
+    (0x15, "Hangul"),
+    (0x16, "ImeOn"),
+    (0x17, "Junja"),
+    (0x18, "Final"),
+    (0x19, "Hanja"),
+    (0x1A, "ImeOff"),
+    (0x1B, "Escape"),
+    (0x1C, "Convert"),
+    (0x1D, "NonConvert"),
+    (0x1E, "Accept"),
+    (0x1F, "ModeChange"),
+    // 탐색
+    (0x20, "Space"),
+    (0x21, "PageUp"),
+    (0x22, "PageDown"),
+    (0x23, "End"),
+    (0x24, "Home"),
+    (0x25, "Left"),
+    (0x26, "Up"),
+    (0x27, "Right"),
+    (0x28, "Down"),
+    (0x29, "Select"),
+    (0x2A, "Print"),
+    (0x2B, "Execute"),
+    (0x2C, "PrintScreen"),
+    (0x2D, "Insert"),
+    (0x2E, "Delete"),
+    (0x2F, "Help"),
+    (0x5B, "LeftWin"),
+    (0x5C, "RightWin"),
+    (0x5D, "Apps"),
+    (0x5F, "Sleep"),
+    // 숫자 패드
+    (0x60, "Numpad0"),
+    (0x61, "Numpad1"),
+    (0x62, "Numpad2"),
+    (0x63, "Numpad3"),
+    (0x64, "Numpad4"),
+    (0x65, "Numpad5"),
+    (0x66, "Numpad6"),
+    (0x67, "Numpad7"),
+    (0x68, "Numpad8"),
+    (0x69, "Numpad9"),
+    (0x6A, "Multiply"),
+    (0x6B, "Add"),
+    (0x6C, "Separator"),
+    (0x6D, "Subtract"),
+    (0x6E, "Decimal"),
+    (0x6F, "Divide"),
+    // 기능 키
+    (0x70, "F1"),
+    (0x71, "F2"),
+    (0x72, "F3"),
+    (0x73, "F4"),
+    (0x74, "F5"),
+    (0x75, "F6"),
+    (0x76, "F7"),
+    (0x77, "F8"),
+    (0x78, "F9"),
+    (0x79, "F10"),
+    (0x7A, "F11"),
+    (0x7B, "F12"),
+    (0x7C, "F13"),
+    (0x7D, "F14"),
+    (0x7E, "F15"),
+    (0x7F, "F16"),
+    (0x80, "F17"),
+    (0x81, "F18"),
+    (0x82, "F19"),
+    (0x83, "F20"),
+    (0x84, "F21"),
+    (0x85, "F22"),
+    (0x86, "F23"),
+    (0x87, "F24"),
+    (0x90, "NumLock"),
+    (0x91, "ScrollLock"),
+    // 좌/우 수식 키
+    (0xA0, "LeftShift"),
+    (0xA1, "RightShift"),
+    (0xA2, "LeftCtrl"),
+    (0xA3, "RightCtrl"),
+    (0xA4, "LeftAlt"),
+    (0xA5, "RightAlt"),
+    // 브라우저·미디어
+    (0xA6, "BrowserBack"),
+    (0xA7, "BrowserForward"),
+    (0xA8, "BrowserRefresh"),
+    (0xA9, "BrowserStop"),
+    (0xAA, "BrowserSearch"),
+    (0xAB, "BrowserFavorites"),
+    (0xAC, "BrowserHome"),
+    (0xAD, "VolumeMute"),
+    (0xAE, "VolumeDown"),
+    (0xAF, "VolumeUp"),
+    (0xB0, "MediaNext"),
+    (0xB1, "MediaPrev"),
+    (0xB2, "MediaStop"),
+    (0xB3, "MediaPlayPause"),
+    (0xB4, "LaunchMail"),
+    (0xB5, "LaunchMediaSelect"),
+    (0xB6, "LaunchApp1"),
+    (0xB7, "LaunchApp2"),
+    // OEM (배열마다 글자가 다르므로 Windows 이름을 따른다)
+    (0xBA, "Oem1"),
+    (0xBB, "OemPlus"),
+    (0xBC, "OemComma"),
+    (0xBD, "OemMinus"),
+    (0xBE, "OemPeriod"),
+    (0xBF, "Oem2"),
+    (0xC0, "Oem3"),
+    (0xDB, "Oem4"),
+    (0xDC, "Oem5"),
+    (0xDD, "Oem6"),
+    (0xDE, "Oem7"),
+    (0xDF, "Oem8"),
+    (0xE2, "Oem102"),
+    (0xE5, "ProcessKey"),
+    (0xE7, "Packet"),
+    (0xF6, "Attn"),
+    (0xF7, "CrSel"),
+    (0xF8, "ExSel"),
+    (0xF9, "EraseEof"),
+    (0xFA, "Play"),
+    (0xFB, "Zoom"),
+    (0xFD, "Pa1"),
+    (0xFE, "OemClear"),
+];
+
+/// 읽을 때만 받는 다른 이름 (같은 코드를 쓰는 일본어 IME 키, 흔한 약칭, US 배열 기준 OEM 글자 이름)
+const ALIASES: &[(&str, u32)] = &[
+    ("Kana", 0x15),
+    ("HanYeong", 0x15),
+    ("Kanji", 0x19),
+    ("Esc", 0x1B),
+    ("Return", 0x0D),
+    ("Control", 0x11),
+    ("Menu", 0x12),
+    ("Caps", 0x14),
+    ("PgUp", 0x21),
+    ("PgDn", 0x22),
+    ("Ins", 0x2D),
+    ("Del", 0x2E),
+    ("LWin", 0x5B),
+    ("RWin", 0x5C),
+    ("ContextMenu", 0x5D),
+    ("LShift", 0xA0),
+    ("RShift", 0xA1),
+    ("LCtrl", 0xA2),
+    ("RCtrl", 0xA3),
+    ("LAlt", 0xA4),
+    ("RAlt", 0xA5),
+    ("LMenu", 0xA4),
+    ("RMenu", 0xA5),
+    ("AltGr", 0xA5),
+    ("Semicolon", 0xBA),
+    ("Slash", 0xBF),
+    ("Backquote", 0xC0),
+    ("LeftBracket", 0xDB),
+    ("Backslash", 0xDC),
+    ("RightBracket", 0xDD),
+    ("Quote", 0xDE),
+];
+
+/// 숫자 키 이름. 숫자만 쓰면 키 코드이므로 (`1`은 0x01) 앞에 `D`를 붙인다.
+const DIGITS: [&str; 10] = ["D0", "D1", "D2", "D3", "D4", "D5", "D6", "D7", "D8", "D9"];
+const LETTERS: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+/// 가상 키 코드의 표준 이름
+pub fn vk_name(vk: u32) -> Option<&'static str> {
+    let index = vk as usize;
+    match vk {
+        0x30..=0x39 => Some(DIGITS[index - 0x30]),
+        0x41..=0x5A => Some(&LETTERS[index - 0x41..=index - 0x41]),
+        _ => NAMES.iter().find(|(v, _)| *v == vk).map(|(_, name)| *name),
+    }
+}
+
+/// 이름(대소문자 무시, 별칭 포함)으로 가상 키 코드 찾기
+pub fn vk_from_name(name: &str) -> Option<u32> {
+    if let [c] = name.as_bytes() {
+        let c = c.to_ascii_uppercase();
+        if c.is_ascii_uppercase() {
+            return Some(u32::from(c));
+        }
+    }
+    if let Some(vk) = digit_from_name(name) {
+        return Some(vk);
+    }
+    NAMES
+        .iter()
+        .map(|&(vk, name)| (name, vk))
+        .chain(ALIASES.iter().copied())
+        .find(|(n, _)| n.eq_ignore_ascii_case(name))
+        .map(|(_, vk)| vk)
+}
+
+/// 숫자 키 이름 (`D1`, `Digit1`)
+fn digit_from_name(name: &str) -> Option<u32> {
+    let digit = ["Digit", "D"].iter().find_map(|prefix| {
+        name.get(..prefix.len())
+            .filter(|p| p.eq_ignore_ascii_case(prefix))
+            .map(|_| &name[prefix.len()..])
+    })?;
+    match digit.as_bytes() {
+        [c] if c.is_ascii_digit() => Some(u32::from(*c)),
+        _ => None,
+    }
+}
+
+/// 키 이름 또는 숫자 키 코드 (`RightAlt`, `0xA5`, `165`)
+///
+/// 숫자만 쓰면 예전 설정처럼 키 코드로 읽으므로 `1`은 0x01이다.
+/// 숫자 키는 `D1`(또는 `Digit1`)로 쓴다.
+pub fn parse_key(s: &str) -> Option<u32> {
+    vk_from_name(s).or_else(|| parse_vk(s))
+}
+
+/// 표시용 키 이름: 이름이 있으면 이름, 없으면 `0xNN`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeyName(pub u32);
+
+impl fmt::Display for KeyName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match vk_name(self.0) {
+            Some(name) => f.write_str(name),
+            None => write!(f, "0x{:02X}", self.0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_code_round_trips() {
+        for vk in 0x00..=0xFF {
+            let text = KeyName(vk).to_string();
+            assert_eq!(parse_key(&text), Some(vk), "{}", text);
+        }
+    }
+
+    #[test]
+    fn names_are_unique() {
+        for (i, (_, name)) in NAMES.iter().enumerate() {
+            assert!(
+                NAMES[i + 1..]
+                    .iter()
+                    .all(|(_, other)| !other.eq_ignore_ascii_case(name)),
+                "{}",
+                name
+            );
+            assert!(ALIASES.iter().all(|(a, _)| !a.eq_ignore_ascii_case(name)));
+        }
+    }
+
+    #[test]
+    fn common_names() {
+        assert_eq!(vk_name(0xA5), Some("RightAlt"));
+        assert_eq!(vk_name(0x41), Some("A"));
+        assert_eq!(vk_name(0x39), Some("D9"));
+        assert_eq!(vk_name(0x87), Some("F24"));
+        assert_eq!(vk_name(0x15), Some("Hangul"));
+        assert_eq!(vk_name(0x07), None);
+
+        assert_eq!(parse_key("rightalt"), Some(0xA5));
+        assert_eq!(parse_key("Kana"), Some(0x15));
+        assert_eq!(parse_key("Hanja"), Some(0x19));
+        assert_eq!(parse_key("a"), Some(0x41));
+        assert_eq!(parse_key("1"), Some(0x01));
+        assert_eq!(parse_key("D1"), Some(0x31));
+        assert_eq!(parse_key("digit0"), Some(0x30));
+        assert_eq!(parse_key("D"), Some(0x44));
+        assert_eq!(parse_key("D10"), None);
+        assert_eq!(parse_key("20"), Some(20));
+        assert_eq!(parse_key("0x14"), Some(0x14));
+        assert_eq!(parse_key("Hyper"), None);
+        assert_eq!(KeyName(0x07).to_string(), "0x07");
+    }
+}
@@ -0,0 +1,14 @@
+//! synergy-hangul-fix의 플랫폼 독립 로직
+//!
+//! Win32 훅/트레이 코드는 바이너리(`app` 모듈)에 있고, 여기에는 Linux에서도
+//! 빌드·테스트할 수 있는 순수 Rust 로직만 둔다.
+
+pub mod caps;
+pub mod config;
+pub mod engine;
+pub mod keys;
+pub mod memory;
+pub mod rules;
+pub mod strategy;
+pub mod trigger;
+pub mod validate;
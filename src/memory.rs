@@ -0,0 +1,218 @@
+//! 창별 IME 상태 기억
+//!
+//! 트리거로 한/영을 바꿀 때 포그라운드 창(또는 프로세스)의 한글 상태를 기록해 두고,
+//! 나중에 그 창이 다시 포그라운드가 되면 기록한 상태로 되돌린다.
+//! 여기에는 무엇을 기억하고 언제 되돌릴지만 있고, 창 전환 감지와 IME 조작은
+//! 호출하는 쪽(WinEvent 훅)이 맡는다.
+
+use std::fmt;
+use std::str::FromStr;
+
+/// 기억해 둘 최대 창/프로세스 수 (넘으면 가장 오래 안 쓴 것부터 잊는다)
+pub const MEMORY_CAPACITY: usize = 256;
+
+/// 상태를 무엇 단위로 기억할지
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RememberScope {
+    /// 기억하지 않음
+    #[default]
+    Off,
+    /// 최상위 창마다
+    Window,
+    /// 프로세스(실행 파일 이름)마다
+    Process,
+}
+
+impl RememberScope {
+    pub const ALL: [RememberScope; 3] = [
+        RememberScope::Off,
+        RememberScope::Window,
+        RememberScope::Process,
+    ];
+
+    /// 설정 파일에 쓰는 이름
+    pub fn name(self) -> &'static str {
+        match self {
+            RememberScope::Off => "off",
+            RememberScope::Window => "window",
+            RememberScope::Process => "process",
+        }
+    }
+}
+
+impl fmt::Display for RememberScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for RememberScope {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        Self::ALL
+            .into_iter()
+            .find(|r| r.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("unknown remember scope '{}'", s))
+    }
+}
+
+/// 기억 항목의 열쇠. 창 핸들은 재사용될 수 있어서 프로세스 이름도 함께 본다.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Key {
+    Window(usize, String),
+    Process(String),
+}
+
+/// 창/프로세스별 한글(native) 상태
+#[derive(Debug, Default)]
+pub struct ImeMemory {
+    scope: RememberScope,
+    /// 오래 안 쓴 것이 앞
+    states: Vec<(Key, bool)>,
+}
+
+impl ImeMemory {
+    /// 빈 기억 (`static` 초기화용)
+    pub const fn new() -> Self {
+        Self {
+            scope: RememberScope::Off,
+            states: Vec::new(),
+        }
+    }
+
+    pub fn scope(&self) -> RememberScope {
+        self.scope
+    }
+
+    /// 단위가 바뀌면 기억을 비운다
+    pub fn set_scope(&mut self, scope: RememberScope) {
+        if scope != self.scope {
+            self.scope = scope;
+            self.states.clear();
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.states.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.states.is_empty()
+    }
+
+    fn key(&self, hwnd: usize, process: &str) -> Option<Key> {
+        let process = process.to_ascii_lowercase();
+        match self.scope {
+            RememberScope::Off => None,
+            RememberScope::Window => Some(Key::Window(hwnd, process)),
+            RememberScope::Process => Some(Key::Process(process)),
+        }
+    }
+
+    /// 트리거로 바꾼 뒤의 상태를 기록한다
+    pub fn record(&mut self, hwnd: usize, process: &str, native: bool) {
+        let Some(key) = self.key(hwnd, process) else {
+            return;
+        };
+        self.states.retain(|(k, _)| *k != key);
+        if self.states.len() >= MEMORY_CAPACITY {
+            self.states.remove(0);
+        }
+        self.states.push((key, native));
+    }
+
+    /// 기록해 둔 상태
+    pub fn recall(&self, hwnd: usize, process: &str) -> Option<bool> {
+        let key = self.key(hwnd, process)?;
+        self.states
+            .iter()
+            .find(|(k, _)| *k == key)
+            .map(|&(_, native)| native)
+    }
+
+    /// 창이 포그라운드가 됐을 때 되돌릴 상태. `current`는 지금 상태(모르면 `None`)이고,
+    /// 기록이 없거나 이미 같은 상태면 `None`
+    pub fn restore(&mut self, hwnd: usize, process: &str, current: Option<bool>) -> Option<bool> {
+        let native = self.recall(hwnd, process)?;
+        // 최근에 쓴 창은 뒤로 보내서 오래 남긴다
+        if let Some(key) = self.key(hwnd, process) {
+            if let Some(i) = self.states.iter().position(|(k, _)| *k == key) {
+                let entry = self.states.remove(i);
+                self.states.push(entry);
+            }
+        }
+        (current != Some(native)).then_some(native)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn off_remembers_nothing() {
+        let mut memory = ImeMemory::new();
+        memory.record(1, "a.exe", true);
+        assert!(memory.is_empty());
+        assert_eq!(memory.restore(1, "a.exe", Some(false)), None);
+    }
+
+    #[test]
+    fn window_scope_restores_each_window() {
+        let mut memory = ImeMemory::new();
+        memory.set_scope(RememberScope::Window);
+        memory.record(1, "notepad.exe", true);
+        memory.record(2, "notepad.exe", false);
+
+        assert_eq!(memory.restore(1, "notepad.exe", Some(false)), Some(true));
+        assert_eq!(memory.restore(2, "notepad.exe", Some(true)), Some(false));
+        // 이미 같은 상태면 건드리지 않고, 모르면 되돌린다
+        assert_eq!(memory.restore(1, "notepad.exe", Some(true)), None);
+        assert_eq!(memory.restore(1, "notepad.exe", None), Some(true));
+        // 기록이 없는 창, 핸들이 재사용된 다른 프로세스
+        assert_eq!(memory.restore(3, "notepad.exe", Some(false)), None);
+        assert_eq!(memory.restore(1, "code.exe", Some(false)), None);
+
+        // 다시 기록하면 덮어쓴다
+        memory.record(1, "NOTEPAD.EXE", false);
+        assert_eq!(memory.recall(1, "notepad.exe"), Some(false));
+        assert_eq!(memory.len(), 2);
+    }
+
+    #[test]
+    fn process_scope_shares_state_between_windows() {
+        let mut memory = ImeMemory::new();
+        memory.set_scope(RememberScope::Process);
+        memory.record(1, "code.exe", true);
+        assert_eq!(memory.restore(99, "Code.exe", Some(false)), Some(true));
+
+        // 단위를 바꾸면 비운다
+        memory.set_scope(RememberScope::Window);
+        assert!(memory.is_empty());
+    }
+
+    #[test]
+    fn least_recently_used_is_forgotten_first() {
+        let mut memory = ImeMemory::new();
+        memory.set_scope(RememberScope::Window);
+        for hwnd in 0..MEMORY_CAPACITY {
+            memory.record(hwnd, "a.exe", true);
+        }
+        // 0번 창을 다시 쓰면 1번 창이 가장 오래된 항목이 된다
+        memory.restore(0, "a.exe", Some(false));
+        memory.record(MEMORY_CAPACITY, "a.exe", true);
+        assert_eq!(memory.len(), MEMORY_CAPACITY);
+        assert_eq!(memory.recall(0, "a.exe"), Some(true));
+        assert_eq!(memory.recall(1, "a.exe"), None);
+    }
+
+    #[test]
+    fn scope_names_round_trip() {
+        for scope in RememberScope::ALL {
+            assert_eq!(scope.to_string().parse(), Ok(scope));
+        }
+        assert!("forever".parse::<RememberScope>().is_err());
+    }
+}
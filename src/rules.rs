@@ -0,0 +1,363 @@
+//! 앱별 규칙
+//!
+//! 포그라운드 윈도우의 프로세스 이름이나 윈도우 클래스에 따라 트리거를 끄거나,
+//! 동작을 바꾸거나, IME 전환 방식을 고정한다 (예: 터미널에서는 Caps Lock을 그대로 둔다).
+//! 창이 포커스를 받을 때 맞출 입력 모드도 같은 조건으로 정한다 (예: IDE는 늘 영문으로).
+//! 위에 적힌 규칙이 먼저 적용되고, 처음 일치한 규칙 하나만 쓴다.
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::engine::Action;
+use crate::trigger::Trigger;
+
+/// 규칙을 비교할 포그라운드 윈도우 정보
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Window {
+    /// 실행 파일 이름 (`mstsc.exe`)
+    pub process: String,
+    /// 윈도우 클래스 이름 (`TscShellContainerClass`)
+    pub class: String,
+}
+
+impl fmt::Display for Window {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} [{}]", self.process, self.class)
+    }
+}
+
+// ── 전환 방식 ─────────────────────────────────────────────────────────────
+
+/// IME를 바꾸는 방법
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Method {
+    /// IMM API를 먼저 쓰고, 안 되면 키 주입
+    #[default]
+    Auto,
+    /// IMM API(IMM 컨텍스트, 기본 IME 창)만 쓴다 (실패해도 키를 주입하지 않음)
+    Imm,
+    /// IMM API를 건너뛰고 바로 `SendInput`으로 키 주입
+    SendInput,
+}
+
+impl Method {
+    pub const ALL: [Method; 3] = [Method::Auto, Method::Imm, Method::SendInput];
+
+    /// 설정 파일에 쓰는 이름
+    pub fn name(self) -> &'static str {
+        match self {
+            Method::Auto => "auto",
+            Method::Imm => "imm",
+            Method::SendInput => "send_input",
+        }
+    }
+}
+
+impl fmt::Display for Method {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Method {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        Self::ALL
+            .into_iter()
+            .find(|m| m.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("unknown method '{}'", s))
+    }
+}
+
+// ── 입력 모드 ─────────────────────────────────────────────────────────────
+
+/// 포커스를 받을 때 맞출 한/영 모드
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InputMode {
+    Hangul,
+    English,
+}
+
+impl InputMode {
+    pub const ALL: [InputMode; 2] = [InputMode::Hangul, InputMode::English];
+
+    /// 설정 파일에 쓰는 이름
+    pub fn name(self) -> &'static str {
+        match self {
+            InputMode::Hangul => "hangul",
+            InputMode::English => "english",
+        }
+    }
+
+    /// `IME_CMODE_NATIVE`가 켜져 있어야 하는지
+    pub fn is_native(self) -> bool {
+        self == InputMode::Hangul
+    }
+}
+
+impl fmt::Display for InputMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for InputMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        Self::ALL
+            .into_iter()
+            .find(|m| m.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("unknown input mode '{}'", s))
+    }
+}
+
+// ── 규칙 ──────────────────────────────────────────────────────────────────
+
+/// 조건(프로세스/클래스/트리거)과 효과(끄기/동작/방식)
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Rule {
+    /// 실행 파일 이름. `.exe`는 생략할 수 있고 대소문자는 무시한다.
+    pub process: Option<String>,
+    /// 윈도우 클래스 이름 (대소문자 무시)
+    pub class: Option<String>,
+    /// 이 트리거에만 적용 (없으면 모든 트리거)
+    pub key: Option<Trigger>,
+    /// 트리거를 끄고 키를 원래대로 통과시킨다
+    pub disable: bool,
+    /// 트리거의 동작 대신 실행할 동작
+    pub action: Option<Action>,
+    pub method: Method,
+    /// 창이 포그라운드가 될 때 맞출 모드 (`key`와 상관없이 적용)
+    pub mode: Option<InputMode>,
+}
+
+impl Rule {
+    /// 윈도우 조건이 하나도 없는 규칙은 어디에도 일치하지 않는다
+    pub fn has_condition(&self) -> bool {
+        self.process.is_some() || self.class.is_some()
+    }
+
+    /// 아무것도 바꾸지 않는 규칙인지
+    pub fn has_effect(&self) -> bool {
+        self.affects_trigger() || self.mode.is_some()
+    }
+
+    /// 트리거 처리를 바꾸는 규칙인지 (`mode`만 있는 규칙은 트리거에 쓰지 않는다)
+    pub fn affects_trigger(&self) -> bool {
+        self.disable || self.action.is_some() || self.method != Method::Auto
+    }
+
+    /// 윈도우 조건(프로세스/클래스)만 비교
+    pub fn matches_window(&self, window: &Window) -> bool {
+        self.has_condition()
+            && self
+                .process
+                .as_deref()
+                .is_none_or(|p| same_process(p, &window.process))
+            && self
+                .class
+                .as_deref()
+                .is_none_or(|c| c.eq_ignore_ascii_case(&window.class))
+    }
+
+    pub fn matches(&self, window: &Window, trigger: Trigger) -> bool {
+        self.matches_window(window) && self.key.is_none_or(|k| k == trigger)
+    }
+}
+
+fn same_process(pattern: &str, name: &str) -> bool {
+    let strip = |s: &str| {
+        let lower = s.to_ascii_lowercase();
+        match lower.strip_suffix(".exe") {
+            Some(stem) => stem.to_string(),
+            None => lower,
+        }
+    };
+    strip(pattern) == strip(name)
+}
+
+impl fmt::Display for Rule {
+    /// `process=mstsc.exe key=CapsLock → disable`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut conditions = Vec::new();
+        if let Some(process) = &self.process {
+            conditions.push(format!("process={}", process));
+        }
+        if let Some(class) = &self.class {
+            conditions.push(format!("class={}", class));
+        }
+        if let Some(key) = self.key {
+            conditions.push(format!("key={}", key));
+        }
+        let mut effects = Vec::new();
+        if self.disable {
+            effects.push("disable".to_string());
+        }
+        if let Some(action) = self.action {
+            effects.push(format!("action={}", action));
+        }
+        if self.method != Method::Auto {
+            effects.push(format!("method={}", self.method));
+        }
+        if let Some(mode) = self.mode {
+            effects.push(format!("mode={}", mode));
+        }
+        if effects.is_empty() {
+            effects.push("no effect".to_string());
+        }
+        write!(f, "{} → {}", conditions.join(" "), effects.join(" "))
+    }
+}
+
+/// 트리거에 처음 일치한 규칙과 그 인덱스
+pub fn find<'a>(rules: &'a [Rule], window: &Window, trigger: Trigger) -> Option<(usize, &'a Rule)> {
+    rules
+        .iter()
+        .enumerate()
+        .find(|(_, rule)| rule.affects_trigger() && rule.matches(window, trigger))
+}
+
+/// 포커스를 받은 창에 처음 일치한, `mode`가 있는 규칙
+pub fn find_mode<'a>(rules: &'a [Rule], window: &Window) -> Option<(usize, &'a Rule, InputMode)> {
+    rules.iter().enumerate().find_map(|(index, rule)| {
+        let mode = rule.mode?;
+        rule.matches_window(window).then_some((index, rule, mode))
+    })
+}
+
+/// 훅에서 일치한 규칙 (로그와 IME 전환 방식에 쓴다)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RuleMatch {
+    pub index: usize,
+    pub rule: Rule,
+    pub window: Window,
+}
+
+impl fmt::Display for RuleMatch {
+    /// `#2 process=mstsc.exe → method=send_input (mstsc.exe [TscShellContainerClass])`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{} {} ({})", self.index + 1, self.rule, self.window)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn window(process: &str, class: &str) -> Window {
+        Window {
+            process: process.to_string(),
+            class: class.to_string(),
+        }
+    }
+
+    #[test]
+    fn process_and_class_conditions() {
+        let terminal = Rule {
+            process: Some("WindowsTerminal".to_string()),
+            disable: true,
+            ..Rule::default()
+        };
+        assert!(terminal.matches(&window("windowsterminal.exe", "X"), Trigger::single(0x14)));
+        assert!(!terminal.matches(&window("cmd.exe", "X"), Trigger::single(0x14)));
+
+        let rdp = Rule {
+            process: Some("mstsc.exe".to_string()),
+            class: Some("TscShellContainerClass".to_string()),
+            key: Some(Trigger::single(0x14)),
+            ..Rule::default()
+        };
+        let rdp_window = window("MSTSC.EXE", "tscshellcontainerclass");
+        assert!(rdp.matches(&rdp_window, Trigger::single(0x14)));
+        assert!(!rdp.matches(&rdp_window, Trigger::single(0xA5)));
+        assert!(!rdp.matches(&window("mstsc.exe", "Other"), Trigger::single(0x14)));
+
+        // 윈도우 조건이 없으면 일치하지 않는다
+        let empty = Rule {
+            disable: true,
+            ..Rule::default()
+        };
+        assert!(!empty.matches(&rdp_window, Trigger::single(0x14)));
+    }
+
+    #[test]
+    fn first_matching_rule_wins() {
+        let rules = [
+            Rule {
+                class: Some("ConsoleWindowClass".to_string()),
+                disable: true,
+                ..Rule::default()
+            },
+            Rule {
+                process: Some("cmd.exe".to_string()),
+                action: Some(Action::SetEnglish),
+                ..Rule::default()
+            },
+        ];
+        let console = window("cmd.exe", "ConsoleWindowClass");
+        assert_eq!(find(&rules, &console, Trigger::single(0x14)).unwrap().0, 0);
+        let other = window("cmd.exe", "Other");
+        assert_eq!(find(&rules, &other, Trigger::single(0x14)).unwrap().0, 1);
+        assert!(find(&rules, &window("a.exe", "b"), Trigger::single(0x14)).is_none());
+    }
+
+    #[test]
+    fn focus_mode_uses_the_same_conditions() {
+        let rules = [
+            Rule {
+                process: Some("code".to_string()),
+                mode: Some(InputMode::English),
+                ..Rule::default()
+            },
+            Rule {
+                process: Some("code.exe".to_string()),
+                disable: true,
+                ..Rule::default()
+            },
+            Rule {
+                class: Some("KakaoTalkEdit".to_string()),
+                key: Some(Trigger::single(0x14)),
+                mode: Some(InputMode::Hangul),
+                ..Rule::default()
+            },
+        ];
+        let code = window("Code.exe", "Chrome_WidgetWin_1");
+        let (index, _, mode) = find_mode(&rules, &code).unwrap();
+        assert_eq!((index, mode), (0, InputMode::English));
+        // mode만 있는 규칙은 트리거 규칙을 가리지 않는다
+        assert_eq!(find(&rules, &code, Trigger::single(0x14)).unwrap().0, 1);
+
+        // key 조건은 포커스 모드에 영향이 없다
+        let chat = window("kakaotalk.exe", "KakaoTalkEdit");
+        let (index, _, mode) = find_mode(&rules, &chat).unwrap();
+        assert_eq!((index, mode), (2, InputMode::Hangul));
+        assert!(find_mode(&rules, &window("cmd.exe", "X")).is_none());
+    }
+
+    #[test]
+    fn display_and_method_names() {
+        let rule = Rule {
+            process: Some("mstsc.exe".to_string()),
+            key: Some(Trigger::single(0x14)),
+            action: Some(Action::SetHangul),
+            method: Method::SendInput,
+            ..Rule::default()
+        };
+        assert_eq!(
+            rule.to_string(),
+            "process=mstsc.exe key=CapsLock → action=set_hangul method=send_input"
+        );
+        for method in Method::ALL {
+            assert_eq!(method.to_string().parse(), Ok(method));
+        }
+        assert!("registry".parse::<Method>().is_err());
+        for mode in InputMode::ALL {
+            assert_eq!(mode.to_string().parse(), Ok(mode));
+        }
+        assert!("japanese".parse::<InputMode>().is_err());
+    }
+}
@@ -0,0 +1,395 @@
+//! IME 전환 전략
+//!
+//! 포그라운드 창에서 `ImmGetContext`가 실패하는 앱(브라우저 자식 창, UWP/Chromium,
+//! 콘솔 호스트)이 많아서, 여러 방법을 차례로 시도한다:
+//!
+//! 1. `imm` — 포커스가 있는 자식 창(`GetGUIThreadInfo`)이나 포그라운드 창의 IMM 컨텍스트
+//! 2. `ime_window` — 기본 IME 창에 `WM_IME_CONTROL` (`IMC_SETOPENSTATUS`/`IMC_SETCONVERSIONMODE`)
+//! 3. `send_input` — `VK_HANGUL` 같은 IME 키 주입
+//!
+//! 앱(프로세스)마다 마지막으로 성공한 방법을 기억해 두고 다음에는 그것부터 시도한다.
+//! 호출이 성공해도 앱이 무시하는 경우가 있어서, 잠시 뒤 변환 모드를 다시 읽어 보고
+//! 바뀌지 않았으면 다음 방법으로 넘어간다. 방법별 성공/실패 횟수도 센다.
+//! 여기에는 순서와 기억, 판정만 있고, 실제 Win32 호출은 `app` 모듈이 한다.
+
+use std::fmt;
+
+use crate::engine::{Action, IME_CMODE_NATIVE};
+use crate::rules::Method;
+
+/// 기억해 둘 최대 프로세스 수 (넘으면 가장 오래 안 쓴 것부터 잊는다)
+pub const CACHE_CAPACITY: usize = 64;
+
+/// 전환한 뒤 다시 읽어 보기까지 기다리는 기본 시간 (ms)
+pub const DEFAULT_VERIFY_MS: u32 = 150;
+
+/// IME를 바꾸는 구체적인 방법
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Strategy {
+    /// IMM 컨텍스트에 `ImmSetConversionStatus`
+    Imm,
+    /// 기본 IME 창에 `WM_IME_CONTROL`
+    ImeWindow,
+    /// IME 키 주입 (`SendInput`)
+    SendInput,
+}
+
+impl Strategy {
+    /// 기본 시도 순서
+    pub const ALL: [Strategy; 3] = [Strategy::Imm, Strategy::ImeWindow, Strategy::SendInput];
+
+    fn index(self) -> usize {
+        match self {
+            Strategy::Imm => 0,
+            Strategy::ImeWindow => 1,
+            Strategy::SendInput => 2,
+        }
+    }
+
+    /// 로그에 쓰는 이름
+    pub fn name(self) -> &'static str {
+        match self {
+            Strategy::Imm => "imm",
+            Strategy::ImeWindow => "ime_window",
+            Strategy::SendInput => "send_input",
+        }
+    }
+
+    /// 규칙의 `method`가 허용하는 방법들 (`imm`은 키를 주입하지 않는 방법 전부)
+    pub fn allowed(method: Method) -> &'static [Strategy] {
+        match method {
+            Method::Auto => &Self::ALL,
+            Method::Imm => &[Strategy::Imm, Strategy::ImeWindow],
+            Method::SendInput => &[Strategy::SendInput],
+        }
+    }
+}
+
+impl fmt::Display for Strategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// 한 방법을 시도한 결과
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    /// 적용함. 바꾼 뒤의 변환 모드를 알면 함께
+    Applied(Option<u32>),
+    /// 이 방법은 쓸 수 없음 (다음 방법으로)
+    Failed(&'static str),
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Outcome::Applied(Some(conversion)) => write!(f, "ok conversion=0x{:08X}", conversion),
+            Outcome::Applied(None) => f.write_str("ok"),
+            Outcome::Failed(reason) => write!(f, "failed ({})", reason),
+        }
+    }
+}
+
+// ── 확인 ──────────────────────────────────────────────────────────────────
+
+/// 적용된 시도 (확인을 기다린다)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Attempt {
+    pub process: String,
+    pub strategy: Strategy,
+    /// 바꾼 뒤의 변환 모드 (모르면 `None`)
+    pub conversion: Option<u32>,
+    /// 확인에 실패하면 이어서 시도할 방법
+    pub remaining: Vec<Strategy>,
+}
+
+impl Attempt {
+    /// 다시 읽은 변환 모드로 한/영 상태가 기대대로 바뀌었는지 판정한다
+    pub fn check(&self, conversion: Option<u32>) -> Verdict {
+        match (self.conversion, conversion) {
+            (Some(expected), Some(actual)) => {
+                if (expected ^ actual) & IME_CMODE_NATIVE == 0 {
+                    Verdict::Confirmed
+                } else {
+                    Verdict::Failed
+                }
+            }
+            _ => Verdict::Unknown,
+        }
+    }
+
+    /// 확인에 실패했을 때 다음 방법으로 실행할 동작.
+    /// 토글을 되풀이하면 늦게 적용된 경우 원래대로 돌아가므로 목표 상태로 바꾼다.
+    pub fn retry_action(&self) -> Option<Action> {
+        let expected = self.conversion?;
+        if self.remaining.is_empty() {
+            return None;
+        }
+        Some(if expected & IME_CMODE_NATIVE != 0 {
+            Action::SetHangul
+        } else {
+            Action::SetEnglish
+        })
+    }
+}
+
+/// 한 방법의 판정
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Verdict {
+    /// 다시 읽어 보니 바뀌어 있음
+    Confirmed,
+    /// 호출이 실패했거나, 다시 읽어 보니 그대로임
+    Failed,
+    /// 확인할 수 없음 (변환 모드를 읽을 수 없거나 확인을 끔)
+    Unknown,
+}
+
+impl fmt::Display for Verdict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Verdict::Confirmed => "confirmed",
+            Verdict::Failed => "failed",
+            Verdict::Unknown => "unknown",
+        })
+    }
+}
+
+/// 방법 하나의 횟수
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Counts {
+    pub confirmed: u32,
+    pub failed: u32,
+    pub unknown: u32,
+}
+
+/// 방법별 성공/실패 횟수
+#[derive(Debug, Default)]
+pub struct StrategyStats {
+    counts: [Counts; 3],
+}
+
+impl StrategyStats {
+    /// 빈 통계 (`static` 초기화용)
+    pub const fn new() -> Self {
+        let zero = Counts {
+            confirmed: 0,
+            failed: 0,
+            unknown: 0,
+        };
+        Self { counts: [zero; 3] }
+    }
+
+    pub fn record(&mut self, strategy: Strategy, verdict: Verdict) {
+        let counts = &mut self.counts[strategy.index()];
+        let count = match verdict {
+            Verdict::Confirmed => &mut counts.confirmed,
+            Verdict::Failed => &mut counts.failed,
+            Verdict::Unknown => &mut counts.unknown,
+        };
+        *count = count.saturating_add(1);
+    }
+
+    pub fn get(&self, strategy: Strategy) -> Counts {
+        self.counts[strategy.index()]
+    }
+}
+
+impl fmt::Display for StrategyStats {
+    /// `imm 3/1 · ime_window 0/0 · send_input 2/0 (?1)` (성공/실패, 확인 못 한 횟수)
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, strategy) in Strategy::ALL.into_iter().enumerate() {
+            let c = self.get(strategy);
+            if i > 0 {
+                f.write_str(" · ")?;
+            }
+            write!(f, "{} {}/{}", strategy, c.confirmed, c.failed)?;
+            if c.unknown > 0 {
+                write!(f, " (?{})", c.unknown)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// ── 앱별 기억 ─────────────────────────────────────────────────────────────
+
+/// 프로세스별로 마지막에 성공한 방법
+#[derive(Debug, Default)]
+pub struct StrategyCache {
+    /// 오래 안 쓴 것이 앞. 프로세스 이름은 소문자
+    entries: Vec<(String, Strategy)>,
+}
+
+impl StrategyCache {
+    /// 빈 기억 (`static` 초기화용)
+    pub const fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get(&self, process: &str) -> Option<Strategy> {
+        let process = process.to_ascii_lowercase();
+        self.entries
+            .iter()
+            .find(|(p, _)| *p == process)
+            .map(|&(_, strategy)| strategy)
+    }
+
+    /// 시도할 순서: 기억해 둔 방법을 맨 앞에, 나머지는 기본 순서대로
+    pub fn plan(&self, process: &str, method: Method) -> Vec<Strategy> {
+        let allowed = Strategy::allowed(method);
+        let mut order = allowed.to_vec();
+        if let Some(cached) = self.get(process).filter(|s| allowed.contains(s)) {
+            order.retain(|&s| s != cached);
+            order.insert(0, cached);
+        }
+        order
+    }
+
+    /// 성공한 방법을 기록한다 (프로세스 이름을 모르면 기록하지 않는다)
+    pub fn record(&mut self, process: &str, strategy: Strategy) {
+        if process.is_empty() {
+            return;
+        }
+        let process = process.to_ascii_lowercase();
+        self.entries.retain(|(p, _)| *p != process);
+        if self.entries.len() >= CACHE_CAPACITY {
+            self.entries.remove(0);
+        }
+        self.entries.push((process, strategy));
+    }
+
+    /// 모든 방법이 실패하면 기억을 지운다
+    pub fn forget(&mut self, process: &str) {
+        let process = process.to_ascii_lowercase();
+        self.entries.retain(|(p, _)| *p != process);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_order_and_method_restrictions() {
+        let cache = StrategyCache::new();
+        assert_eq!(cache.plan("a.exe", Method::Auto), Strategy::ALL.to_vec());
+        assert_eq!(
+            cache.plan("a.exe", Method::Imm),
+            vec![Strategy::Imm, Strategy::ImeWindow]
+        );
+        assert_eq!(
+            cache.plan("a.exe", Method::SendInput),
+            vec![Strategy::SendInput]
+        );
+    }
+
+    #[test]
+    fn remembered_strategy_goes_first() {
+        let mut cache = StrategyCache::new();
+        cache.record("chrome.exe", Strategy::ImeWindow);
+        assert_eq!(
+            cache.plan("Chrome.exe", Method::Auto),
+            vec![Strategy::ImeWindow, Strategy::Imm, Strategy::SendInput]
+        );
+        // 규칙이 허용하지 않는 방법은 기억해 둔 것이라도 쓰지 않는다
+        assert_eq!(
+            cache.plan("chrome.exe", Method::SendInput),
+            vec![Strategy::SendInput]
+        );
+        // 다른 프로세스에는 영향 없음
+        assert_eq!(cache.plan("code.exe", Method::Auto), Strategy::ALL.to_vec());
+
+        cache.record("chrome.exe", Strategy::SendInput);
+        assert_eq!(cache.get("chrome.exe"), Some(Strategy::SendInput));
+        assert_eq!(cache.len(), 1);
+        cache.forget("CHROME.EXE");
+        assert!(cache.is_empty());
+
+        // 프로세스 이름을 모르면 기록하지 않는다
+        cache.record("", Strategy::Imm);
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn least_recently_recorded_is_forgotten_first() {
+        let mut cache = StrategyCache::new();
+        for i in 0..=CACHE_CAPACITY {
+            cache.record(&format!("{}.exe", i), Strategy::Imm);
+        }
+        assert_eq!(cache.len(), CACHE_CAPACITY);
+        assert_eq!(cache.get("0.exe"), None);
+        assert_eq!(cache.get("1.exe"), Some(Strategy::Imm));
+    }
+
+    fn attempt(conversion: Option<u32>, remaining: &[Strategy]) -> Attempt {
+        Attempt {
+            process: "chrome.exe".to_string(),
+            strategy: Strategy::Imm,
+            conversion,
+            remaining: remaining.to_vec(),
+        }
+    }
+
+    #[test]
+    fn verification_compares_the_native_bit() {
+        let hangul = attempt(Some(IME_CMODE_NATIVE | 0x8), &[Strategy::SendInput]);
+        assert_eq!(hangul.check(Some(IME_CMODE_NATIVE)), Verdict::Confirmed);
+        assert_eq!(hangul.check(Some(0x8)), Verdict::Failed);
+        assert_eq!(hangul.check(None), Verdict::Unknown);
+        // 다음 방법은 토글이 아니라 목표 상태로
+        assert_eq!(hangul.retry_action(), Some(Action::SetHangul));
+        assert_eq!(
+            attempt(Some(0), &[Strategy::SendInput]).retry_action(),
+            Some(Action::SetEnglish)
+        );
+
+        // 결과를 모르거나 남은 방법이 없으면 더 시도하지 않는다
+        assert_eq!(attempt(None, &[]).check(Some(0)), Verdict::Unknown);
+        assert_eq!(attempt(None, &[Strategy::SendInput]).retry_action(), None);
+        assert_eq!(attempt(Some(0), &[]).retry_action(), None);
+    }
+
+    #[test]
+    fn stats_count_per_strategy() {
+        let mut stats = StrategyStats::new();
+        stats.record(Strategy::Imm, Verdict::Confirmed);
+        stats.record(Strategy::Imm, Verdict::Failed);
+        stats.record(Strategy::SendInput, Verdict::Confirmed);
+        stats.record(Strategy::SendInput, Verdict::Unknown);
+        assert_eq!(
+            stats.get(Strategy::Imm),
+            Counts {
+                confirmed: 1,
+                failed: 1,
+                unknown: 0
+            }
+        );
+        assert_eq!(
+            stats.to_string(),
+            "imm 1/1 · ime_window 0/0 · send_input 1/0 (?1)"
+        );
+    }
+
+    #[test]
+    fn names_and_outcomes() {
+        assert_eq!(Strategy::ImeWindow.to_string(), "ime_window");
+        assert_eq!(
+            Outcome::Applied(Some(1)).to_string(),
+            "ok conversion=0x00000001"
+        );
+        assert_eq!(
+            Outcome::Failed("no IMM context").to_string(),
+            "failed (no IMM context)"
+        );
+    }
+}
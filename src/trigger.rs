@@ -0,0 +1,403 @@
+//! 트리거 키 정의
+//!
+//! 트리거는 필요한 수식 키 집합과 메인 키 하나로 이루어진다 (예: `Shift+Space`).
+//! 메인 키에는 스캔 코드를 덧붙여 물리 키까지 고정할 수 있다 (예: `LeftAlt@0xE038`).
+//! 설정 파일에서는 `+`로 이은 문자열로 읽고 쓴다.
+
+use std::fmt;
+use std::ops::{BitAnd, BitOr, Not};
+use std::str::FromStr;
+
+use crate::keys::{parse_key, KeyName};
+
+// ── 수식 키 ───────────────────────────────────────────────────────────────
+
+/// 수식 키 집합 (좌/우 구분)
+///
+/// 같은 종류의 좌/우 비트가 모두 켜져 있으면 "어느 쪽이든"을 뜻한다.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Modifiers(u8);
+
+impl Modifiers {
+    pub const NONE: Self = Self(0);
+    pub const LSHIFT: Self = Self(1 << 0);
+    pub const RSHIFT: Self = Self(1 << 1);
+    pub const LCTRL: Self = Self(1 << 2);
+    pub const RCTRL: Self = Self(1 << 3);
+    pub const LALT: Self = Self(1 << 4);
+    pub const RALT: Self = Self(1 << 5);
+    pub const LWIN: Self = Self(1 << 6);
+    pub const RWIN: Self = Self(1 << 7);
+
+    pub const SHIFT: Self = Self(Self::LSHIFT.0 | Self::RSHIFT.0);
+    pub const CTRL: Self = Self(Self::LCTRL.0 | Self::RCTRL.0);
+    pub const ALT: Self = Self(Self::LALT.0 | Self::RALT.0);
+    pub const WIN: Self = Self(Self::LWIN.0 | Self::RWIN.0);
+
+    /// 종류별 (이름, 양쪽, 왼쪽, 오른쪽)
+    const KINDS: [(&'static str, Self, Self, Self); 4] = [
+        ("Ctrl", Self::CTRL, Self::LCTRL, Self::RCTRL),
+        ("Shift", Self::SHIFT, Self::LSHIFT, Self::RSHIFT),
+        ("Alt", Self::ALT, Self::LALT, Self::RALT),
+        ("Win", Self::WIN, Self::LWIN, Self::RWIN),
+    ];
+
+    /// 좌/우 수식 키의 가상 키 코드
+    pub const VKS: [(u32, Self); 8] = [
+        (0xA0, Self::LSHIFT),
+        (0xA1, Self::RSHIFT),
+        (0xA2, Self::LCTRL),
+        (0xA3, Self::RCTRL),
+        (0xA4, Self::LALT),
+        (0xA5, Self::RALT),
+        (0x5B, Self::LWIN),
+        (0x5C, Self::RWIN),
+    ];
+
+    pub fn from_vk(vk: u32) -> Option<Self> {
+        Self::VKS.iter().find(|(v, _)| *v == vk).map(|(_, m)| *m)
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// 눌린 수식 키 `held`가 이 요구 조건을 만족하는지.
+    /// 요구하지 않은 종류의 수식 키가 더 눌려 있으면 맞지 않는다
+    /// (`Space` 트리거는 Ctrl+Space 같은 단축키에서 동작하지 않는다).
+    pub fn satisfied_by(self, held: Self) -> bool {
+        Self::KINDS.iter().all(|(_, kind, _, _)| {
+            let required = self.0 & kind.0;
+            if required == 0 {
+                held.0 & kind.0 == 0
+            } else {
+                held.0 & required != 0
+            }
+        })
+    }
+
+    /// 지정된 좌/우 수식 키 비트 수
+    pub fn count(self) -> u32 {
+        self.0.count_ones()
+    }
+
+    fn parse_name(name: &str) -> Option<Self> {
+        let lower = name.to_ascii_lowercase();
+        let (side, base) = if let Some(rest) = lower.strip_prefix("left") {
+            (Some(false), rest)
+        } else if let Some(rest) = lower.strip_prefix("right") {
+            (Some(true), rest)
+        } else if let Some(rest) = lower.strip_prefix('l') {
+            (Some(false), rest)
+        } else if let Some(rest) = lower.strip_prefix('r') {
+            (Some(true), rest)
+        } else {
+            (None, lower.as_str())
+        };
+        let base = match base {
+            "control" => "ctrl",
+            "menu" => "alt",
+            other => other,
+        };
+        Self::KINDS
+            .iter()
+            .find(|(n, _, _, _)| n.eq_ignore_ascii_case(base))
+            .map(|&(_, both, left, right)| match side {
+                None => both,
+                Some(false) => left,
+                Some(true) => right,
+            })
+    }
+}
+
+impl BitOr for Modifiers {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl BitAnd for Modifiers {
+    type Output = Self;
+
+    fn bitand(self, rhs: Self) -> Self {
+        Self(self.0 & rhs.0)
+    }
+}
+
+impl Not for Modifiers {
+    type Output = Self;
+
+    fn not(self) -> Self {
+        Self(!self.0)
+    }
+}
+
+impl fmt::Display for Modifiers {
+    /// `Ctrl+LShift`처럼 `+`로 이어 쓴다 (양쪽이면 접두사 없이)
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut first = true;
+        for &(name, both, left, right) in &Self::KINDS {
+            let side = if self.contains(both) {
+                ""
+            } else if self.contains(left) {
+                "L"
+            } else if self.contains(right) {
+                "R"
+            } else {
+                continue;
+            };
+            if !first {
+                f.write_str("+")?;
+            }
+            write!(f, "{}{}", side, name)?;
+            first = false;
+        }
+        Ok(())
+    }
+}
+
+// ── 스캔 코드 ─────────────────────────────────────────────────────────────
+
+/// 물리 키 위치: 스캔 코드 + 확장 키(`LLKHF_EXTENDED`, E0 접두사) 여부
+///
+/// Synergy는 Right Alt를 Left Alt(0xA4)로 보내는 등 가상 키 코드가 모호할 때가
+/// 있어서, 스캔 코드까지 보면 실제로 전달된 키를 구분할 수 있다.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ScanCode {
+    pub code: u32,
+    pub extended: bool,
+}
+
+impl ScanCode {
+    pub const fn new(code: u32, extended: bool) -> Self {
+        Self { code, extended }
+    }
+}
+
+impl fmt::Display for ScanCode {
+    /// 확장 키는 관례대로 `0xE038`처럼 E0 접두사를 붙인다
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.extended {
+            write!(f, "0xE0{:02X}", self.code)
+        } else {
+            write!(f, "0x{:02X}", self.code)
+        }
+    }
+}
+
+impl FromStr for ScanCode {
+    type Err = ParseTriggerError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let code = parse_vk(s)
+            .filter(|&c| c <= 0xFFFF)
+            .ok_or_else(|| ParseTriggerError(format!("unknown scan code '{}'", s)))?;
+        Ok(match code >> 8 {
+            0 => Self::new(code, false),
+            0xE0 => Self::new(code & 0xFF, true),
+            _ => return Err(ParseTriggerError(format!("unknown scan code '{}'", s))),
+        })
+    }
+}
+
+// ── 트리거 ────────────────────────────────────────────────────────────────
+
+/// 수식 키 + 메인 키 (+ 선택적으로 메인 키의 스캔 코드)
+///
+/// `mods`에 없는 종류의 수식 키가 함께 눌려 있으면 맞지 않는다. 그래서 `Space` 트리거는
+/// Ctrl+Space나 Win+Space 같은 단축키를 가로채지 않는다.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Trigger {
+    pub mods: Modifiers,
+    pub key: u32,
+    /// `None`이면 가상 키 코드만 비교한다
+    pub scan: Option<ScanCode>,
+}
+
+impl Trigger {
+    /// 수식 키 없는 단일 키 트리거
+    pub const fn single(key: u32) -> Self {
+        Self::chord(Modifiers::NONE, key)
+    }
+
+    pub const fn chord(mods: Modifiers, key: u32) -> Self {
+        Self {
+            mods,
+            key,
+            scan: None,
+        }
+    }
+
+    /// 스캔 코드와 확장 키 여부까지 일치해야 하는 트리거
+    pub const fn with_scan(self, scan: ScanCode) -> Self {
+        Self {
+            scan: Some(scan),
+            ..self
+        }
+    }
+
+    /// 메인 키가 이 트리거의 키인지 (수식 키 상태는 보지 않음)
+    pub fn matches_key(&self, vk: u32, scan: ScanCode) -> bool {
+        self.key == vk && self.scan.is_none_or(|s| s == scan)
+    }
+}
+
+impl fmt::Display for Trigger {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.mods.is_empty() {
+            write!(f, "{}+", self.mods)?;
+        }
+        write!(f, "{}", KeyName(self.key))?;
+        if let Some(scan) = self.scan {
+            write!(f, "@{}", scan)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseTriggerError(String);
+
+impl fmt::Display for ParseTriggerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid trigger: {}", self.0)
+    }
+}
+
+impl std::error::Error for ParseTriggerError {}
+
+impl FromStr for Trigger {
+    type Err = ParseTriggerError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts: Vec<&str> = s.split('+').map(str::trim).collect();
+        let key_part = parts.pop().unwrap_or_default();
+        let (key_part, scan) = match key_part.split_once('@') {
+            Some((key, scan)) => (key.trim(), Some(scan.trim().parse()?)),
+            None => (key_part, None),
+        };
+        let key = parse_key(key_part)
+            .ok_or_else(|| ParseTriggerError(format!("unknown key '{}'", key_part)))?;
+
+        let mut mods = Modifiers::NONE;
+        for name in parts {
+            mods = mods
+                | Modifiers::parse_name(name)
+                    .ok_or_else(|| ParseTriggerError(format!("unknown modifier '{}'", name)))?;
+        }
+        Ok(Self { mods, key, scan })
+    }
+}
+
+/// 16진수(`0x` 접두사) 또는 10진수 키 코드
+pub fn parse_vk(val: &str) -> Option<u32> {
+    if let Some(hex) = val.strip_prefix("0x").or(val.strip_prefix("0X")) {
+        u32::from_str_radix(hex, 16).ok()
+    } else {
+        val.parse::<u32>().ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_single_key() {
+        assert_eq!("0xA4".parse(), Ok(Trigger::single(0xA4)));
+        assert_eq!("20".parse(), Ok(Trigger::single(20)));
+        assert_eq!("RightAlt".parse(), Ok(Trigger::single(0xA5)));
+        assert_eq!("capslock".parse(), Ok(Trigger::single(0x14)));
+    }
+
+    #[test]
+    fn parse_chord_with_sides() {
+        assert_eq!(
+            "Shift+0x20".parse(),
+            Ok(Trigger::chord(Modifiers::SHIFT, 0x20))
+        );
+        assert_eq!(
+            "lctrl + RShift + 0xA5".parse(),
+            Ok(Trigger::chord(Modifiers::LCTRL | Modifiers::RSHIFT, 0xA5))
+        );
+        assert_eq!(
+            "LeftCtrl+Space".parse(),
+            Ok(Trigger::chord(Modifiers::LCTRL, 0x20))
+        );
+        assert!("Hyper+0x20".parse::<Trigger>().is_err());
+        assert!("Shift+".parse::<Trigger>().is_err());
+    }
+
+    #[test]
+    fn display_round_trips() {
+        for t in [
+            Trigger::single(0x14),
+            Trigger::chord(Modifiers::CTRL, 0xA5),
+            Trigger::chord(Modifiers::LSHIFT | Modifiers::RALT | Modifiers::WIN, 0x20),
+            Trigger::single(0xA4).with_scan(ScanCode::new(0x38, true)),
+            Trigger::chord(Modifiers::SHIFT, 0x20).with_scan(ScanCode::new(0x39, false)),
+        ] {
+            assert_eq!(t.to_string().parse(), Ok(t));
+        }
+        assert_eq!(
+            Trigger::chord(Modifiers::CTRL, 0xA5).to_string(),
+            "Ctrl+RightAlt"
+        );
+        assert_eq!(Trigger::single(0x07).to_string(), "0x07");
+    }
+
+    #[test]
+    fn parse_scan_code_suffix() {
+        assert_eq!(
+            "0xA4@0xE038".parse(),
+            Ok(Trigger::single(0xA4).with_scan(ScanCode::new(0x38, true)))
+        );
+        assert_eq!(
+            "Ctrl + 0xA4 @ 0x38".parse(),
+            Ok(Trigger::chord(Modifiers::CTRL, 0xA4).with_scan(ScanCode::new(0x38, false)))
+        );
+        assert_eq!(
+            Trigger::single(0xA4)
+                .with_scan(ScanCode::new(0x38, true))
+                .to_string(),
+            "LeftAlt@0xE038"
+        );
+        assert!("0xA4@".parse::<Trigger>().is_err());
+        assert!("0xA4@0xE138".parse::<Trigger>().is_err());
+    }
+
+    #[test]
+    fn scan_code_narrows_key_match() {
+        let left = ScanCode::new(0x38, false);
+        let right = ScanCode::new(0x38, true);
+        assert!(Trigger::single(0xA4).matches_key(0xA4, right));
+        assert!(Trigger::single(0xA4)
+            .with_scan(right)
+            .matches_key(0xA4, right));
+        assert!(!Trigger::single(0xA4)
+            .with_scan(right)
+            .matches_key(0xA4, left));
+        assert!(!Trigger::single(0xA4)
+            .with_scan(right)
+            .matches_key(0xA5, right));
+    }
+
+    #[test]
+    fn either_side_and_specific_side() {
+        assert!(Modifiers::SHIFT.satisfied_by(Modifiers::RSHIFT));
+        assert!(!Modifiers::LSHIFT.satisfied_by(Modifiers::LSHIFT | Modifiers::LCTRL));
+        assert!(!Modifiers::LSHIFT.satisfied_by(Modifiers::RSHIFT));
+        assert!(!(Modifiers::SHIFT | Modifiers::CTRL).satisfied_by(Modifiers::LSHIFT));
+        assert!(
+            (Modifiers::SHIFT | Modifiers::CTRL).satisfied_by(Modifiers::RSHIFT | Modifiers::LCTRL)
+        );
+        assert!(!Modifiers::NONE.satisfied_by(Modifiers::LALT));
+        assert!(Modifiers::NONE.satisfied_by(Modifiers::NONE));
+    }
+}
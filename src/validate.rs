@@ -0,0 +1,842 @@
+//! 설정 파일 검증
+//!
+//! serde는 첫 오류에서 멈추므로, 먼저 TOML 문서를 직접 훑으며 모든 오류와 경고를
+//! 줄 번호와 함께 모은 뒤 오류가 없을 때만 [`Config`]로 읽는다.
+//! 알 수 없는 키와 중복 트리거는 경고(무시하고 계속), 잘못된 값은 오류다.
+
+use std::fmt;
+use std::ops::{Range, RangeInclusive};
+use std::str::FromStr;
+
+use toml_edit::{ImDocument, Item, TableLike, Value};
+
+use crate::config::Config;
+use crate::engine::{Action, HoldAction, Source, TriggerMode};
+use crate::keys::KeyName;
+use crate::memory::RememberScope;
+use crate::rules::{InputMode, Method};
+use crate::trigger::Trigger;
+
+/// 탭/홀드 임계값으로 받아들이는 범위 (ms)
+pub const HOLD_MS_RANGE: RangeInclusive<i64> = 50..=5000;
+
+/// 전환 확인 대기 시간으로 받아들이는 범위 (ms, 0은 확인 안 함)
+pub const VERIFY_MS_RANGE: RangeInclusive<i64> = 0..=2000;
+
+/// 표시기를 보여 주는 시간으로 받아들이는 범위 (ms)
+pub const INDICATOR_MS_RANGE: RangeInclusive<i64> = 100..=10000;
+
+/// 트리거로 쓸 수 있는 가상 키 코드 범위
+pub const VK_RANGE: RangeInclusive<u32> = 0x01..=0xFE;
+
+/// 왼쪽/오른쪽 구분 없는 `Shift`/`Ctrl`/`Alt`. 키보드 훅은 왼쪽/오른쪽 키로만 알려 주므로
+/// 트리거 키로는 눌리지 않는다 (수식 키 조건 `Shift+...`로는 쓸 수 있다).
+const GENERIC_MODIFIERS: RangeInclusive<u32> = 0x10..=0x12;
+
+const TOP_KEYS: &[&str] = &[
+    "enabled",
+    "profile",
+    "mode",
+    "repeat_toggles",
+    "tap_hold",
+    "triggers",
+    "profiles",
+    "rules",
+    "ime",
+    "caps_lock",
+    "indicator",
+    "logging",
+    "ui",
+];
+const TAP_HOLD_KEYS: &[&str] = &["hold_ms", "hold_action"];
+const TRIGGER_KEYS: &[&str] = &["key", "action", "source"];
+const PROFILE_KEYS: &[&str] = &["name", "triggers"];
+const RULE_KEYS: &[&str] = &[
+    "process", "class", "key", "disable", "action", "method", "mode",
+];
+const IME_KEYS: &[&str] = &["remember", "verify_ms"];
+const CAPS_LOCK_KEYS: &[&str] = &["preserve", "shift_passthrough"];
+const INDICATOR_KEYS: &[&str] = &["enabled", "duration_ms", "on_focus"];
+const LOGGING_KEYS: &[&str] = &["debug_window", "file"];
+const UI_KEYS: &[&str] = &["confirm_learned_key", "confirm_remove"];
+
+// ── 진단 ──────────────────────────────────────────────────────────────────
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        })
+    }
+}
+
+/// 오류/경고 하나 (`line`은 1부터, 위치를 모르면 0)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    /// `line 12: error: unknown action 'x'`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}: {}", self.line, self.severity, self.message)
+    }
+}
+
+/// 검증 결과. 오류가 없으면 `config`가 채워진다.
+#[derive(Clone, Debug, Default)]
+pub struct Report {
+    pub config: Option<Config>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Report {
+    pub fn errors(&self) -> usize {
+        self.count(Severity::Error)
+    }
+
+    pub fn warnings(&self) -> usize {
+        self.count(Severity::Warning)
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.errors() > 0
+    }
+
+    fn count(&self, severity: Severity) -> usize {
+        self.diagnostics
+            .iter()
+            .filter(|d| d.severity == severity)
+            .count()
+    }
+}
+
+// ── 검증 ──────────────────────────────────────────────────────────────────
+
+/// 설정 파일 내용을 검증하고, 오류가 없으면 읽은 설정을 함께 돌려준다
+pub fn check(text: &str) -> Report {
+    let mut checker = Checker {
+        text,
+        diagnostics: Vec::new(),
+    };
+
+    let doc = match ImDocument::parse(text) {
+        Ok(doc) => doc,
+        Err(e) => {
+            checker.report(Severity::Error, e.span(), e.message().trim().to_string());
+            return checker.finish(None);
+        }
+    };
+
+    let dropped = checker.check_document(doc.as_table());
+    if checker
+        .diagnostics
+        .iter()
+        .any(|d| d.severity == Severity::Error)
+    {
+        return checker.finish(None);
+    }
+
+    match toml::from_str::<Config>(text) {
+        Ok(mut config) => {
+            // 경고로 알린 중복 항목은 처음 것만 남긴다
+            config.triggers = without(config.triggers, &dropped.triggers);
+            for (profile, triggers) in config.profiles.iter_mut().zip(&dropped.profile_triggers) {
+                profile.triggers = without(std::mem::take(&mut profile.triggers), triggers);
+            }
+            config.profiles = without(config.profiles, &dropped.profiles);
+            checker.finish(Some(config))
+        }
+        Err(e) => {
+            checker.report(Severity::Error, e.span(), e.message().trim().to_string());
+            checker.finish(None)
+        }
+    }
+}
+
+/// 경고 후 건너뛸 항목의 인덱스
+#[derive(Default)]
+struct Dropped {
+    triggers: Vec<usize>,
+    profiles: Vec<usize>,
+    /// 프로필마다 (인덱스는 `[[profiles]]` 순서 그대로)
+    profile_triggers: Vec<Vec<usize>>,
+}
+
+fn without<T>(items: Vec<T>, dropped: &[usize]) -> Vec<T> {
+    items
+        .into_iter()
+        .enumerate()
+        .filter(|(i, _)| !dropped.contains(i))
+        .map(|(_, item)| item)
+        .collect()
+}
+
+struct Checker<'a> {
+    text: &'a str,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Checker<'_> {
+    fn finish(mut self, config: Option<Config>) -> Report {
+        self.diagnostics.sort_by_key(|d| d.line);
+        Report {
+            config,
+            diagnostics: self.diagnostics,
+        }
+    }
+
+    fn line(&self, span: Option<Range<usize>>) -> usize {
+        span.map_or(0, |r| {
+            self.text[..r.start.min(self.text.len())]
+                .matches('\n')
+                .count()
+                + 1
+        })
+    }
+
+    fn report(&mut self, severity: Severity, span: Option<Range<usize>>, message: String) {
+        let line = self.line(span);
+        self.diagnostics.push(Diagnostic {
+            severity,
+            line,
+            message,
+        });
+    }
+
+    /// 문서 전체를 검사하고, 무시할 중복 항목을 돌려준다
+    fn check_document(&mut self, root: &dyn TableLike) -> Dropped {
+        self.unknown_keys(root, TOP_KEYS, None);
+        self.boolean(root, "enabled");
+        self.string(root, "profile");
+        self.parsed::<TriggerMode>(root, "mode");
+        self.boolean(root, "repeat_toggles");
+
+        if let Some(table) = self.section(root, "tap_hold") {
+            self.unknown_keys(table, TAP_HOLD_KEYS, Some("tap_hold"));
+            self.integer(table, "hold_ms", HOLD_MS_RANGE);
+            if let Some((HoldAction::Modifier(vk), span)) =
+                self.parsed::<HoldAction>(table, "hold_action")
+            {
+                self.vk_in_range(vk, span);
+            }
+        }
+        if let Some(table) = self.section(root, "ime") {
+            self.unknown_keys(table, IME_KEYS, Some("ime"));
+            self.parsed::<RememberScope>(table, "remember");
+            self.integer(table, "verify_ms", VERIFY_MS_RANGE);
+        }
+        if let Some(table) = self.section(root, "caps_lock") {
+            self.unknown_keys(table, CAPS_LOCK_KEYS, Some("caps_lock"));
+            self.boolean(table, "preserve");
+            self.boolean(table, "shift_passthrough");
+        }
+        if let Some(table) = self.section(root, "indicator") {
+            self.unknown_keys(table, INDICATOR_KEYS, Some("indicator"));
+            self.boolean(table, "enabled");
+            self.integer(table, "duration_ms", INDICATOR_MS_RANGE);
+            self.boolean(table, "on_focus");
+        }
+        if let Some(table) = self.section(root, "logging") {
+            self.unknown_keys(table, LOGGING_KEYS, Some("logging"));
+            self.boolean(table, "debug_window");
+            self.string(table, "file");
+        }
+        if let Some(table) = self.section(root, "ui") {
+            self.unknown_keys(table, UI_KEYS, Some("ui"));
+            self.boolean(table, "confirm_learned_key");
+            self.boolean(table, "confirm_remove");
+        }
+        let mut dropped = Dropped {
+            triggers: self.triggers(root, "triggers"),
+            ..Dropped::default()
+        };
+        self.profiles(root, &mut dropped);
+        self.rules(root);
+        dropped
+    }
+
+    fn rules(&mut self, root: &dyn TableLike) {
+        for (table, span) in self.tables(root, "rules") {
+            self.unknown_keys(table, RULE_KEYS, Some("rules"));
+            self.string(table, "process");
+            self.string(table, "class");
+            self.boolean(table, "disable");
+            let action = self.parsed::<Action>(table, "action");
+            let method = self.parsed::<Method>(table, "method");
+            let mode = self.parsed::<InputMode>(table, "mode");
+            if let Some((trigger, key_span)) = self.parsed::<Trigger>(table, "key") {
+                self.vk_in_range(trigger.key, key_span.clone());
+                self.side_specific(trigger.key, key_span);
+            }
+
+            if table.get("process").is_none() && table.get("class").is_none() {
+                self.report(
+                    Severity::Warning,
+                    span,
+                    "rule has neither 'process' nor 'class' and never matches".to_string(),
+                );
+            } else if table.get("disable").and_then(|d| d.as_bool()) != Some(true)
+                && action.is_none()
+                && method.map_or(table.get("method").is_none(), |(m, _)| m == Method::Auto)
+                && mode.is_none()
+                && table.get("mode").is_none()
+            {
+                self.report(
+                    Severity::Warning,
+                    span,
+                    "rule has no effect (set 'disable', 'action', 'method' or 'mode')".to_string(),
+                );
+            }
+        }
+    }
+
+    /// `[[name]]` 배열의 테이블들. 배열이 아니거나 테이블이 아닌 항목은 오류
+    fn tables<'t>(
+        &mut self,
+        root: &'t dyn TableLike,
+        name: &str,
+    ) -> Vec<(&'t dyn TableLike, Option<Range<usize>>)> {
+        let Some((key, item)) = root.get_key_value(name) else {
+            return Vec::new();
+        };
+        if let Some(tables) = item.as_array_of_tables() {
+            tables
+                .iter()
+                .map(|t| (t as &dyn TableLike, t.span()))
+                .collect()
+        } else if let Some(array) = item.as_array() {
+            let mut entries = Vec::new();
+            for value in array.iter() {
+                match value.as_inline_table() {
+                    Some(t) => entries.push((t as &dyn TableLike, t.span())),
+                    None => self.report(
+                        Severity::Error,
+                        value.span(),
+                        format!("each entry of '{}' must be a table", name),
+                    ),
+                }
+            }
+            entries
+        } else {
+            self.report(
+                Severity::Error,
+                key.span(),
+                format!("'{}' must be an array of tables ([[{}]])", name, name),
+            );
+            Vec::new()
+        }
+    }
+
+    /// 프로필 이름과 각 프로필의 트리거 테이블. `profile`이 가리키는 프로필이 없으면 경고
+    fn profiles(&mut self, root: &dyn TableLike, dropped: &mut Dropped) {
+        let mut names: Vec<(String, usize)> = Vec::new();
+        for (index, (table, span)) in self.tables(root, "profiles").into_iter().enumerate() {
+            self.unknown_keys(table, PROFILE_KEYS, Some("profiles"));
+            dropped
+                .profile_triggers
+                .push(self.triggers(table, "profiles.triggers"));
+
+            self.string(table, "name");
+            let Some((name, name_span)) = self
+                .value(table, "name")
+                .and_then(|(v, span)| Some((v.as_str()?.to_string(), span)))
+            else {
+                if table.get("name").is_none() {
+                    self.report(
+                        Severity::Error,
+                        span,
+                        "profile entry is missing 'name'".to_string(),
+                    );
+                }
+                continue;
+            };
+            match names.iter().find(|(n, _)| *n == name) {
+                Some(&(_, first)) => {
+                    self.report(
+                        Severity::Warning,
+                        name_span,
+                        format!(
+                            "duplicate profile '{}' (first defined on line {}), ignored",
+                            name, first
+                        ),
+                    );
+                    dropped.profiles.push(index);
+                }
+                None => {
+                    let line = self.line(name_span);
+                    names.push((name, line));
+                }
+            }
+        }
+
+        if let Some((active, span)) = self
+            .value(root, "profile")
+            .and_then(|(v, span)| Some((v.as_str()?, span)))
+        {
+            if !names.iter().any(|(n, _)| n == active) {
+                self.report(
+                    Severity::Warning,
+                    span,
+                    format!("unknown profile '{}', using top-level triggers", active),
+                );
+            }
+        }
+    }
+
+    /// `name` 아래 트리거 항목들을 검사하고, 무시할 중복 트리거의 인덱스를 돌려준다
+    fn triggers(&mut self, table: &dyn TableLike, name: &str) -> Vec<usize> {
+        let key = name.rsplit('.').next().unwrap_or(name);
+        let entries = self.tables(table, key);
+
+        let mut seen: Vec<(Trigger, Source, usize)> = Vec::new();
+        let mut duplicates = Vec::new();
+        for (index, (table, span)) in entries.into_iter().enumerate() {
+            self.unknown_keys(table, TRIGGER_KEYS, Some(name));
+            self.parsed::<Action>(table, "action");
+            let source = self
+                .parsed::<Source>(table, "source")
+                .map_or(Source::Any, |(s, _)| s);
+            let Some((trigger, key_span)) = self.parsed::<Trigger>(table, "key") else {
+                if table.get("key").is_none() {
+                    self.report(
+                        Severity::Error,
+                        span,
+                        "trigger entry is missing 'key'".to_string(),
+                    );
+                }
+                continue;
+            };
+            self.vk_in_range(trigger.key, key_span.clone());
+            self.side_specific(trigger.key, key_span.clone());
+
+            let line = self.line(key_span.clone());
+            match seen.iter().find(|(t, s, _)| *t == trigger && *s == source) {
+                Some(&(_, _, first)) => {
+                    self.report(
+                        Severity::Warning,
+                        key_span,
+                        format!(
+                            "duplicate trigger '{}' (first defined on line {}), ignored",
+                            trigger, first
+                        ),
+                    );
+                    duplicates.push(index);
+                }
+                None => seen.push((trigger, source, line)),
+            }
+        }
+        duplicates
+    }
+
+    fn unknown_keys(&mut self, table: &dyn TableLike, known: &[&str], section: Option<&str>) {
+        for (name, _) in table.iter() {
+            if known.contains(&name) {
+                continue;
+            }
+            let span = table.get_key_value(name).and_then(|(k, _)| k.span());
+            let message = match section {
+                Some(section) => format!("unknown key '{}' in [{}], ignored", name, section),
+                None => format!("unknown key '{}', ignored", name),
+            };
+            self.report(Severity::Warning, span, message);
+        }
+    }
+
+    /// 하위 테이블. 있는데 테이블이 아니면 오류
+    fn section<'t>(&mut self, root: &'t dyn TableLike, name: &str) -> Option<&'t dyn TableLike> {
+        let (key, item) = root.get_key_value(name)?;
+        let table = item.as_table_like();
+        if table.is_none() {
+            self.report(
+                Severity::Error,
+                key.span(),
+                format!("'{}' must be a table ([{}])", name, name),
+            );
+        }
+        table
+    }
+
+    /// 값과 그 위치. 없으면 `None`
+    fn value<'t>(
+        &self,
+        table: &'t dyn TableLike,
+        name: &str,
+    ) -> Option<(&'t Value, Option<Range<usize>>)> {
+        let (key, item) = table.get_key_value(name)?;
+        let span = item.span().or_else(|| key.span());
+        match item {
+            Item::Value(value) => Some((value, span)),
+            _ => None,
+        }
+    }
+
+    fn type_error(&mut self, table: &dyn TableLike, name: &str, expected: &str) {
+        let span = table.get_key_value(name).and_then(|(k, _)| k.span());
+        self.report(
+            Severity::Error,
+            span,
+            format!("'{}' must be {}", name, expected),
+        );
+    }
+
+    fn boolean(&mut self, table: &dyn TableLike, name: &str) {
+        if table.get(name).is_some()
+            && self
+                .value(table, name)
+                .and_then(|(v, _)| v.as_bool())
+                .is_none()
+        {
+            self.type_error(table, name, "true or false");
+        }
+    }
+
+    fn string(&mut self, table: &dyn TableLike, name: &str) {
+        if table.get(name).is_some()
+            && self
+                .value(table, name)
+                .and_then(|(v, _)| v.as_str())
+                .is_none()
+        {
+            self.type_error(table, name, "a string");
+        }
+    }
+
+    fn integer(&mut self, table: &dyn TableLike, name: &str, range: RangeInclusive<i64>) {
+        if table.get(name).is_none() {
+            return;
+        }
+        match self
+            .value(table, name)
+            .and_then(|(v, span)| Some((v.as_integer()?, span)))
+        {
+            Some((n, _)) if range.contains(&n) => {}
+            Some((n, span)) => self.report(
+                Severity::Error,
+                span,
+                format!(
+                    "'{}' = {} is out of range ({}..={})",
+                    name,
+                    n,
+                    range.start(),
+                    range.end()
+                ),
+            ),
+            None => self.type_error(table, name, "an integer"),
+        }
+    }
+
+    /// 문자열 값을 `FromStr`로 읽는다. 형식이 틀리면 오류를 남기고 `None`
+    fn parsed<T>(&mut self, table: &dyn TableLike, name: &str) -> Option<(T, Option<Range<usize>>)>
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        table.get(name)?;
+        let Some((s, span)) = self
+            .value(table, name)
+            .and_then(|(v, span)| Some((v.as_str()?, span)))
+        else {
+            self.type_error(table, name, "a string");
+            return None;
+        };
+        match s.parse() {
+            Ok(value) => Some((value, span)),
+            Err(e) => {
+                self.report(Severity::Error, span, format!("'{}': {}", name, e));
+                None
+            }
+        }
+    }
+
+    fn vk_in_range(&mut self, vk: u32, span: Option<Range<usize>>) {
+        if !VK_RANGE.contains(&vk) {
+            self.report(
+                Severity::Error,
+                span,
+                format!(
+                    "key code 0x{:X} is out of range (0x{:02X}..=0x{:02X})",
+                    vk,
+                    VK_RANGE.start(),
+                    VK_RANGE.end()
+                ),
+            );
+        }
+    }
+
+    fn side_specific(&mut self, vk: u32, span: Option<Range<usize>>) {
+        if GENERIC_MODIFIERS.contains(&vk) {
+            self.report(
+                Severity::Warning,
+                span,
+                format!(
+                    "'{0}' never fires as a trigger key, use 'Left{0}' or 'Right{0}'",
+                    KeyName(vk)
+                ),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::DEFAULT_INDICATOR_MS;
+
+    fn lines(report: &Report) -> Vec<String> {
+        report.diagnostics.iter().map(|d| d.to_string()).collect()
+    }
+
+    #[test]
+    fn valid_file_has_no_diagnostics() {
+        let report = check(
+            "mode = \"tap\"\n\
+             [[triggers]]\n\
+             key = \"Ctrl+0xA5\"\n\
+             action = \"set_hangul\"\n",
+        );
+        assert!(report.diagnostics.is_empty(), "{:?}", lines(&report));
+        assert_eq!(report.config.unwrap().mode, TriggerMode::Tap);
+    }
+
+    #[test]
+    fn collects_every_error_with_line_numbers() {
+        let report = check(
+            "mode = \"hold\"\n\
+             repeat_toggles = \"yes\"\n\
+             \n\
+             [tap_hold]\n\
+             hold_ms = 999999\n\
+             \n\
+             [[triggers]]\n\
+             key = \"Hyper+0x20\"\n\
+             \n\
+             [[triggers]]\n\
+             key = \"0x14\"\n\
+             action = \"launch_rockets\"\n",
+        );
+        assert!(report.config.is_none());
+        assert_eq!(report.errors(), 5);
+        assert_eq!(
+            lines(&report),
+            vec![
+                "line 1: error: 'mode': unknown trigger mode 'hold'",
+                "line 2: error: 'repeat_toggles' must be true or false",
+                "line 5: error: 'hold_ms' = 999999 is out of range (50..=5000)",
+                "line 8: error: 'key': invalid trigger: unknown modifier 'Hyper'",
+                "line 12: error: 'action': unknown action 'launch_rockets'",
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_keys_are_warnings() {
+        let report = check(
+            "trigger_key = \"0x14\"\n\
+             [ui]\n\
+             confirm_remove = false\n\
+             colour = \"red\"\n",
+        );
+        assert_eq!(
+            lines(&report),
+            vec![
+                "line 1: warning: unknown key 'trigger_key', ignored",
+                "line 4: warning: unknown key 'colour' in [ui], ignored",
+            ]
+        );
+        let config = report.config.expect("warnings don't block loading");
+        assert!(!config.ui.confirm_remove);
+    }
+
+    #[test]
+    fn generic_modifier_keys_warn() {
+        let report = check(
+            "[[triggers]]\n\
+             key = \"Alt\"\n\
+             [[triggers]]\n\
+             key = \"Shift+Space\"\n\
+             [[rules]]\n\
+             process = \"code.exe\"\n\
+             key = \"0x11\"\n\
+             disable = true\n",
+        );
+        assert_eq!(
+            lines(&report),
+            vec![
+                "line 2: warning: 'Alt' never fires as a trigger key, use 'LeftAlt' or 'RightAlt'",
+                "line 7: warning: 'Ctrl' never fires as a trigger key, use 'LeftCtrl' or 'RightCtrl'",
+            ]
+        );
+        assert!(report.config.is_some());
+    }
+
+    #[test]
+    fn duplicate_triggers_warn_and_keep_first() {
+        let report = check(
+            "[[triggers]]\n\
+             key = \"0xA5\"\n\
+             action = \"set_hangul\"\n\
+             [[triggers]]\n\
+             key = \"0xA5\"\n\
+             action = \"set_english\"\n\
+             [[triggers]]\n\
+             key = \"0xA5\"\n\
+             source = \"injected\"\n",
+        );
+        assert_eq!(
+            lines(&report),
+            vec![
+                "line 5: warning: duplicate trigger 'RightAlt' (first defined on line 2), ignored"
+            ]
+        );
+        let config = report.config.unwrap();
+        assert_eq!(config.triggers.len(), 2);
+        assert_eq!(config.triggers[0].action, Action::SetHangul);
+        assert_eq!(config.triggers[1].source, Source::Injected);
+    }
+
+    #[test]
+    fn rules_are_checked() {
+        let report = check(
+            "[[rules]]\n\
+             process = \"mstsc.exe\"\n\
+             method = \"send_input\"\n\
+             [[rules]]\n\
+             disable = true\n\
+             [[rules]]\n\
+             class = \"Notepad\"\n\
+             [[rules]]\n\
+             process = \"x.exe\"\n\
+             method = \"telepathy\"\n\
+             [[rules]]\n\
+             process = \"code.exe\"\n\
+             mode = \"english\"\n\
+             [[rules]]\n\
+             process = \"y.exe\"\n\
+             mode = \"klingon\"\n",
+        );
+        assert_eq!(
+            lines(&report),
+            vec![
+                "line 4: warning: rule has neither 'process' nor 'class' and never matches",
+                "line 6: warning: rule has no effect (set 'disable', 'action', 'method' or 'mode')",
+                "line 10: error: 'method': unknown method 'telepathy'",
+                "line 16: error: 'mode': unknown input mode 'klingon'",
+            ]
+        );
+    }
+
+    #[test]
+    fn ime_section_is_checked() {
+        let report = check("[ime]\nremember = \"window\"\nverify_ms = 0\n");
+        assert!(report.diagnostics.is_empty(), "{:?}", lines(&report));
+        assert_eq!(report.config.unwrap().ime.verify_ms, 0);
+
+        let report = check("[ime]\nremember = \"forever\"\nverify_ms = 60000\n");
+        assert_eq!(
+            lines(&report),
+            vec![
+                "line 2: error: 'remember': unknown remember scope 'forever'",
+                "line 3: error: 'verify_ms' = 60000 is out of range (0..=2000)",
+            ]
+        );
+    }
+
+    #[test]
+    fn indicator_section_is_checked() {
+        let report = check("[indicator]\nenabled = true\nduration_ms = 50\non_focus = 1\n");
+        assert_eq!(
+            lines(&report),
+            vec![
+                "line 3: error: 'duration_ms' = 50 is out of range (100..=10000)",
+                "line 4: error: 'on_focus' must be true or false",
+            ]
+        );
+        let report = check("[indicator]\nenabled = true\n");
+        let indicator = report.config.unwrap().indicator;
+        assert!(indicator.enabled);
+        assert_eq!(indicator.duration_ms, DEFAULT_INDICATOR_MS);
+    }
+
+    #[test]
+    fn profiles_are_checked() {
+        let report = check(
+            "profile = \"laptop\"\n\
+             [[profiles]]\n\
+             name = \"synergy\"\n\
+             [[profiles.triggers]]\n\
+             key = \"LeftAlt\"\n\
+             [[profiles.triggers]]\n\
+             key = \"LeftAlt\"\n\
+             [[profiles]]\n\
+             name = \"synergy\"\n\
+             color = \"red\"\n",
+        );
+        assert_eq!(
+            lines(&report),
+            vec![
+                "line 1: warning: unknown profile 'laptop', using top-level triggers",
+                "line 7: warning: duplicate trigger 'LeftAlt' (first defined on line 5), ignored",
+                "line 9: warning: duplicate profile 'synergy' (first defined on line 3), ignored",
+                "line 10: warning: unknown key 'color' in [profiles], ignored",
+            ]
+        );
+        let config = report.config.unwrap();
+        assert_eq!(config.profiles.len(), 1);
+        assert_eq!(config.profiles[0].triggers.len(), 1);
+
+        let report = check("[[profiles]]\n[[profiles.triggers]]\nkey = \"Hyper\"\n");
+        assert_eq!(report.errors(), 2, "{:?}", lines(&report));
+    }
+
+    #[test]
+    fn out_of_range_key_codes() {
+        let report = check(
+            "[tap_hold]\n\
+             hold_action = \"0x1A2\"\n\
+             [[triggers]]\n\
+             key = \"0x100\"\n",
+        );
+        assert_eq!(report.errors(), 2);
+        assert_eq!(report.diagnostics[0].line, 2);
+        assert_eq!(report.diagnostics[1].line, 4);
+    }
+
+    #[test]
+    fn syntax_error_has_line() {
+        let report = check("mode = \"press\"\n[ui\n");
+        assert!(report.has_errors());
+        assert_eq!(report.diagnostics[0].line, 2);
+    }
+
+    #[test]
+    fn wrong_section_types() {
+        let report = check("ui = 3\ntriggers = \"0x14\"\n");
+        assert_eq!(
+            lines(&report),
+            vec![
+                "line 1: error: 'ui' must be a table ([ui])",
+                "line 2: error: 'triggers' must be an array of tables ([[triggers]])",
+            ]
+        );
+    }
+
+    #[test]
+    fn inline_trigger_array_is_checked() {
+        let report = check("triggers = [{ key = \"0x14\" }, { action = \"set_hangul\" }]\n");
+        assert_eq!(
+            lines(&report),
+            vec!["line 1: error: trigger entry is missing 'key'"]
+        );
+        assert!(check("triggers = []").config.unwrap().triggers.is_empty());
+    }
+}